@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+
+//ticks of history kept for every probe, enough for the panel zoomed out all the way
+pub const HISTORY: u64 = 20000;
+pub const MAX_PROBES: usize = 16;
+
+//transitions are the ticks where the group changed with its new value, the first one is the value when the history starts
+#[derive(Clone)]
+pub struct Probe {
+    pub group: usize,
+    pub transitions: VecDeque<(u64, bool)>,
+}
+
+impl Probe {
+    //the value the group had after the given tick, None before the probe was added
+    pub fn value_at(&self, tick: u64) -> Option<bool> {
+        let index = self.transitions.partition_point(|transition| transition.0 <= tick);
+        if index == 0 { None } else { Some(self.transitions[index - 1].1) }
+    }
+
+    //catches up with a copy of the same probe that kept recording, takes its newer transitions and drops what it dropped
+    pub fn follow(&mut self, other: &Probe){
+        let start = match self.transitions.back() {
+            Some(last) => other.transitions.partition_point(|transition| transition.0 <= last.0),
+            None => 0
+        };
+        self.transitions.extend(other.transitions.range(start..).copied());
+        while self.transitions.len() > other.transitions.len() {
+            self.transitions.pop_front();
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, value: bool){
+        if self.transitions.back().is_none_or(|transition| transition.1 != value) {
+            self.transitions.push_back((tick, value));
+        }
+        while self.transitions.len() > 1 && self.transitions[1].0 + HISTORY <= tick {
+            self.transitions.pop_front();
+        }
+    }
+}
+
+//the groups shown in the logic analyzer panel, sampled after every tick
+#[derive(Clone, Default)]
+pub struct Analyzer {
+    pub probes: Vec<Probe>,
+}
+
+impl Analyzer {
+    //adds a probe for the group or removes it if there is one, false if there are too many probes already
+    pub fn toggle_probe(&mut self, group: usize, tick: u64, value: bool) -> bool {
+        if let Some(index) = self.probes.iter().position(|probe| probe.group == group) {
+            self.probes.remove(index);
+            return true;
+        }
+        if self.probes.len() >= MAX_PROBES {
+            return false;
+        }
+        self.probes.push(Probe{group, transitions: VecDeque::from([(tick, value)])});
+        true
+    }
+}
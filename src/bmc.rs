@@ -0,0 +1,198 @@
+use crate::content::{ComponentData, ComponentType};
+use crate::export::whole_canvas;
+use crate::program::{get_phase, Opcode};
+use crate::region::{compile_region, describe_group};
+use crate::sat::{Literal, Solver};
+use crate::vectors::TestVectors;
+
+pub const DEFAULT_TICKS: u32 = 20;
+
+pub struct Violation {
+    pub vectors: TestVectors,
+    pub tick: u64,
+    pub group: usize,
+}
+
+//every assert plus the lights and asserts at the given positions
+pub fn find_properties(component_data: &ComponentData, positions: &[(usize, usize)]) -> Result<Vec<usize>, String> {
+    let mut properties: Vec<usize> = (0..component_data.logic_components.len()).filter(|group| component_data.logic_components[*group].component_type == ComponentType::ASSERT).collect();
+    for position in positions.iter(){
+        let component = component_data.array[position.0][position.1];
+        if component.belongs_to == -1 || (component.component_type != ComponentType::LIGHT && component.component_type != ComponentType::ASSERT) {
+            return Err(format!("there is no light or assert at x{} y{}", position.0, position.1));
+        }
+        if !properties.contains(&(component.belongs_to as usize)) {
+            properties.push(component.belongs_to as usize);
+        }
+    }
+    if properties.is_empty() {
+        return Err("there is nothing to check, place an assert or name a light".to_string());
+    }
+    Ok(properties)
+}
+
+//unrolls unit delay mode for the given number of ticks from the state right after compiling and asks the solver
+//for latch toggles that turn a property on. only the groups the properties depend on are encoded
+//the toggles found are replayed in a simulation of the canvas, which also finds the first tick a property is on
+pub fn check(component_data: &ComponentData, properties: &[usize], ticks: u32) -> Result<Option<Violation>, String> {
+    let logic_components = &component_data.logic_components;
+    let mut in_cone = vec![false; logic_components.len()];
+    let mut cone = properties.to_vec();
+    for group in cone.iter(){
+        in_cone[*group] = true;
+    }
+    let mut index = 0;
+    while index < cone.len() {
+        for input in logic_components[cone[index]].component_before.iter(){
+            if !in_cone[*input as usize] {
+                in_cone[*input as usize] = true;
+                cone.push(*input as usize);
+            }
+        }
+        index += 1;
+    }
+    //a group reads groups of earlier phases from this tick and the rest from the last one,
+    //going through the cone phase by phase gives exactly that when the values are overwritten in place
+    cone.sort_by_key(|group| (get_phase(logic_components[*group].component_type), *group));
+    for group in cone.iter(){
+        if logic_components[*group].component_type == ComponentType::FRAMEBUFFER {
+            return Err(format!("{} is in front of a property, framebuffers can't be checked", describe_group(component_data, *group)));
+        }
+    }
+
+    let mut solver = Solver::new();
+    let off = !solver.new_var();
+    solver.add_clause(&[!off]);
+    let mut values = vec![off; logic_components.len()];
+    let mut toggles = vec![];
+    let mut violations = vec![];
+    for tick in 0..ticks {
+        let previous = values.clone();
+        for group in cone.iter().copied(){
+            let inputs: Vec<Literal> = logic_components[group].component_before.iter().map(|input| values[*input as usize]).collect();
+            let opcode = match Opcode::from_component_type(logic_components[group].component_type) {
+                Some(opcode) => opcode,
+                None => continue
+            };
+            let output = match opcode {
+                Opcode::Clock => !previous[group],
+                Opcode::Latch => {
+                    //latches only toggle in ticks where they are dirty: the first one, or after an input changed
+                    let toggle = solver.new_var();
+                    if inputs.is_empty() {
+                        toggles.push((tick, group, toggle));
+                    } else {
+                        let any_input = solver.new_var();
+                        solver.add_or(any_input, &inputs);
+                        if tick == 0 {
+                            solver.add_and(toggle, &[any_input]);
+                        } else {
+                            let mut changes = vec![];
+                            for input in logic_components[group].component_before.iter(){
+                                let change = solver.new_var();
+                                solver.add_xor(change, &[values[*input as usize], previous[*input as usize]]);
+                                changes.push(change);
+                            }
+                            let dirty = solver.new_var();
+                            solver.add_or(dirty, &changes);
+                            solver.add_and(toggle, &[dirty, any_input]);
+                        }
+                    }
+                    let output = solver.new_var();
+                    solver.add_xor(output, &[previous[group], toggle]);
+                    output
+                }
+                _ => {
+                    let output = solver.new_var();
+                    match opcode {
+                        Opcode::Nor => solver.add_or(!output, &inputs),
+                        Opcode::And if inputs.is_empty() => solver.add_clause(&[!output]),
+                        Opcode::And => solver.add_and(output, &inputs),
+                        Opcode::Nand if inputs.is_empty() => solver.add_clause(&[output]),
+                        Opcode::Nand => solver.add_and(!output, &inputs),
+                        Opcode::Xor => solver.add_xor(output, &inputs),
+                        Opcode::Xnor => solver.add_xor(!output, &inputs),
+                        _ => solver.add_or(output, &inputs)
+                    }
+                    output
+                }
+            };
+            values[group] = output;
+        }
+        for property in properties.iter(){
+            violations.push(values[*property]);
+        }
+    }
+    solver.add_clause(&violations);
+    if !solver.solve() {
+        return Ok(None);
+    }
+
+    let mut vectors = TestVectors::default();
+    for (tick, group, toggle) in toggles.iter(){
+        if solver.value(*toggle) {
+            let position = logic_components[*group].elements[0];
+            vectors.toggles.push((*tick as u64, position));
+        }
+    }
+    let mut replay = compile_region(component_data, whole_canvas());
+    for tick in 0..ticks as u64 {
+        vectors.apply(&mut replay)?;
+        replay.update_canvas();
+        if let Some(group) = properties.iter().copied().find(|group| replay.is_group_enabled(*group)) {
+            vectors.toggles.retain(|toggle| toggle.0 <= tick);
+            return Ok(Some(Violation{vectors, tick, group}));
+        }
+    }
+    Err("the solver found a violation that doesn't show up in the simulation".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_canvas::compiled;
+
+    //the assert turns on a few ticks after both latches are on
+    const BOTH_ON: [&str; 3] = ["lw--r", "....&w-r|w-r|w-ra", "lw--r"];
+
+    fn first_tick_on(component_data: &ComponentData, vectors: &TestVectors, group: usize) -> Option<u64> {
+        let mut replay = compile_region(component_data, whole_canvas());
+        for tick in 0..100 {
+            vectors.apply(&mut replay).unwrap();
+            replay.update_canvas();
+            if replay.is_group_enabled(group) {
+                return Some(tick);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn finds_the_violation_at_its_depth(){
+        let component_data = compiled(&BOTH_ON);
+        let properties = find_properties(&component_data, &[]).unwrap();
+        assert_eq!(properties.len(), 1);
+        let both = TestVectors{toggles: vec![(0, (0, 0)), (0, (0, 2))]};
+        let depth = first_tick_on(&component_data, &both, properties[0]).unwrap() + 1;
+        assert!(first_tick_on(&component_data, &TestVectors{toggles: vec![(0, (0, 0))]}, properties[0]).is_none());
+        assert!(check(&component_data, &properties, depth as u32 - 1).unwrap().is_none());
+        let violation = check(&component_data, &properties, depth as u32).unwrap().unwrap();
+        assert_eq!((violation.tick, violation.group), (depth - 1, properties[0]));
+        assert!(violation.vectors.toggles.iter().all(|toggle| toggle.0 <= violation.tick));
+        assert_eq!(first_tick_on(&component_data, &violation.vectors, properties[0]), Some(violation.tick));
+        //the vectors survive being saved as text
+        let parsed = TestVectors::parse(&violation.vectors.to_text()).unwrap();
+        assert_eq!(first_tick_on(&component_data, &parsed, properties[0]), Some(violation.tick));
+    }
+
+    #[test]
+    fn named_lights_are_properties(){
+        let component_data = compiled(&["lw--r", "....^w-rL", "lw--r"]);
+        assert!(find_properties(&component_data, &[]).is_err());
+        assert!(find_properties(&component_data, &[(0, 0)]).is_err());
+        let properties = find_properties(&component_data, &[(8, 1)]).unwrap();
+        let violation = check(&component_data, &properties, DEFAULT_TICKS).unwrap().unwrap();
+        assert!(!violation.vectors.toggles.is_empty());
+        assert_eq!(first_tick_on(&component_data, &violation.vectors, properties[0]), Some(violation.tick));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::content::{ComponentData, ComponentType};
+use crate::region::describe_group;
+
+//a bus is read like a hex display reads its inputs, the first group is the lowest bit
+#[derive(Clone)]
+pub enum Condition {
+    Group{group: usize, on: bool},
+    Bus{groups: Vec<usize>, value: u64},
+}
+
+//met is whether the condition held after the last tick, a breakpoint only triggers when it starts to hold
+#[derive(Clone)]
+pub struct Breakpoint {
+    pub condition: Condition,
+    pub met: bool,
+}
+
+impl Condition {
+    pub fn is_met(&self, component_data: &ComponentData) -> bool {
+        match self {
+            Condition::Group{group, on} => component_data.is_group_enabled(*group) == *on,
+            Condition::Bus{groups, value} => read_bus(component_data, groups) == *value
+        }
+    }
+
+    pub fn groups(&self) -> Vec<usize> {
+        match self {
+            Condition::Group{group, ..} => vec![*group],
+            Condition::Bus{groups, ..} => groups.clone()
+        }
+    }
+
+    pub fn describe(&self, component_data: &ComponentData) -> String {
+        match self {
+            Condition::Group{group, on} => format!("{} turns {}", describe_group(component_data, *group), if *on { "on" } else { "off" }),
+            Condition::Bus{groups, value} => format!("the {} latches from {} equal {}", groups.len(), describe_group(component_data, groups[0]), value)
+        }
+    }
+}
+
+pub fn read_bus(component_data: &ComponentData, groups: &[usize]) -> u64 {
+    let mut value = 0;
+    for (bit, group) in groups.iter().enumerate(){
+        if component_data.is_group_enabled(*group) {
+            value |= 1 << bit;
+        }
+    }
+    value
+}
+
+//the latches in a region ordered left to right, then top to bottom
+pub fn find_bus(component_data: &ComponentData, region: ((i32, i32), (i32, i32))) -> Result<Vec<usize>, String> {
+    let mut groups: Vec<usize> = (0..component_data.logic_components.len()).filter(|group| {
+        let logic_component = &component_data.logic_components[*group];
+        let position = logic_component.elements[0];
+        logic_component.component_type == ComponentType::LATCH && position.0 as i32 >= region.0.0 && position.0 as i32 <= region.1.0 && position.1 as i32 >= region.0.1 && position.1 as i32 <= region.1.1
+    }).collect();
+    groups.sort_by_key(|group| component_data.logic_components[*group].elements[0]);
+    if groups.is_empty() {
+        return Err("there are no latches in the selection".to_string());
+    }
+    if groups.len() > 64 {
+        return Err(format!("a bus can have up to 64 latches, the selection has {}", groups.len()));
+    }
+    Ok(groups)
+}
+
+//decimal, or hexadecimal and binary with 0x and 0b in front. a value that needs more bits than the bus has could never be met
+pub fn parse_value(text: &str, bits: usize) -> Result<u64, String> {
+    let text = text.trim().to_lowercase();
+    let value = if let Some(digits) = text.strip_prefix("0x") {
+        u64::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = text.strip_prefix("0b") {
+        u64::from_str_radix(digits, 2).ok()
+    } else {
+        text.parse().ok()
+    };
+    let Some(value) = value else {
+        return Err(format!("{} is not a number", text));
+    };
+    if bits < 64 && value >> bits != 0 {
+        return Err(format!("{} doesn't fit in a bus of {} latches", text, bits));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_have_to_fit_the_bus(){
+        assert_eq!(parse_value("0xFF", 8), Ok(255));
+        assert_eq!(parse_value(" 0b101 ", 3), Ok(5));
+        assert_eq!(parse_value("18446744073709551615", 64), Ok(u64::MAX));
+        assert!(parse_value("0x1FF", 8).is_err());
+        assert!(parse_value("256", 8).is_err());
+        assert!(parse_value("2", 1).is_err());
+        assert!(parse_value("0x", 8).is_err());
+        assert!(parse_value("ten", 8).is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use crate::content::{ComponentData, ComponentType, DelayMode};
+use crate::{bmc, coverage, critical_path, equivalence, export, faults, import, stats, truth_table};
+use crate::region::describe_group;
+use crate::vectors::TestVectors;
+use crate::{get_default_path, load_array, save_array};
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1))
+}
+
+fn get_number_option(args: &[String], name: &str, default: u32) -> u32 {
+    match get_option(args, name) {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            println!("{} expects a number, using {}", name, default);
+            default
+        }),
+        None => default
+    }
+}
+
+fn get_region_option(args: &[String]) -> Option<((i32, i32), (i32, i32))> {
+    get_named_region_option(args, "--region")
+}
+
+fn get_named_region_option(args: &[String], name: &str) -> Option<((i32, i32), (i32, i32))> {
+    let index = args.iter().position(|arg| arg == name)?;
+    let values: Vec<i32> = args.iter().skip(index + 1).take(4).filter_map(|value| value.parse().ok()).collect();
+    if values.len() != 4 {
+        println!("{} expects x1 y1 x2 y2", name);
+        return None;
+    }
+    let mut region = ((values[0], values[1]), (values[2], values[3]));
+    crate::prepare_selection(&mut region);
+    Some(region)
+}
+
+//every "--property x y", the option can be given more than once
+fn get_property_options(args: &[String]) -> Vec<(usize, usize)> {
+    let mut positions = vec![];
+    for (index, _) in args.iter().enumerate().filter(|(_, arg)| *arg == "--property"){
+        let values: Vec<usize> = args.iter().skip(index + 1).take(2).filter_map(|value| value.parse().ok()).collect();
+        if values.len() != 2 || !ComponentData::are_coordinates_in_bounds(values[0] as i32, values[1] as i32) {
+            println!("--property expects x y");
+            continue;
+        }
+        positions.push((values[0], values[1]));
+    }
+    positions
+}
+
+fn get_vectors_option(args: &[String]) -> Option<TestVectors> {
+    let path = get_option(args, "--vectors")?;
+    match TestVectors::load(std::path::Path::new(path)) {
+        Ok(vectors) => Some(vectors),
+        Err(error) => {
+            println!("couldn't load {}: {}", path, error);
+            None
+        }
+    }
+}
+
+fn get_canvas_path(args: &[String]) -> std::path::PathBuf {
+    match get_option(args, "--canvas") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => get_default_path("canvas.dat")
+    }
+}
+
+fn load_canvas(args: &[String]) -> ComponentData {
+    let mut component_data = ComponentData::default();
+    load_array(&mut component_data, &get_canvas_path(args));
+    if args.iter().any(|arg| arg == "--zero-delay") {
+        component_data.set_delay_mode(DelayMode::Zero);
+    } else if args.iter().any(|arg| arg == "--timed") {
+        component_data.set_delay_mode(DelayMode::Timed);
+    }
+    component_data.set_optimize(args.iter().any(|arg| arg == "--optimize"));
+    component_data.set_heatmap(args.iter().any(|arg| arg == "--heatmap"));
+    component_data
+}
+
+fn compile_canvas(component_data: &mut ComponentData){
+    component_data.compile_scene();
+    if let Some(report) = component_data.optimization.as_ref() {
+        println!("{}", report.summary());
+    }
+    for warning in component_data.ignored_input_warnings() {
+        println!("{}", warning);
+    }
+}
+
+fn print_usage(){
+    println!("usage: pc_simulation [command] [options]");
+    println!("without a command the editor is opened");
+    println!("every command accepts --canvas <file> to use a different canvas than canvas.dat next to the executable");
+    println!("and --zero-delay or --timed to simulate in zero delay or timing mode, --optimize runs the optimization pass before simulating");
+    println!("and --heatmap draws the simulation view as a heatmap of how often every group toggled");
+    println!();
+    println!("  export <file.png|file.ppm> [--sim] [--ticks n] [--scale n] [--region x1 y1 x2 y2]");
+    println!("      saves an image of the canvas, --sim draws the simulation view after n ticks");
+    println!("  record <file.gif> [--ticks n] [--every n] [--delay ms] [--scale n] [--region x1 y1 x2 y2] [--vectors file.txt]");
+    println!("      records the simulation view for n ticks (100 by default) as an animated gif, a frame every n ticks");
+    println!("      while toggling latches as listed in a test vector file");
+    println!("  truth-table [file.csv|file.md] [--region x1 y1 x2 y2]");
+    println!("      prints the truth table of a combinational region, or saves it if a file is given");
+    println!("  equivalence [other.dat] [--region x1 y1 x2 y2] [--other-region x1 y1 x2 y2] [--sat]");
+    println!("      checks that the other canvas (or the same one) computes the same outputs for every input,");
+    println!("      exhaustively for up to {} inputs and with a sat solver above that or with --sat", truth_table::MAX_INPUTS);
+    println!("  bmc [file.txt] [--ticks n] [--property x y]");
+    println!("      searches latch toggles that turn an assert (or the light given with --property) on within n ticks ({} by default)", bmc::DEFAULT_TICKS);
+    println!("      in unit delay mode and saves them as a test vector file if a file is given");
+    println!("  replay <file.txt> [--ticks n] [--property x y] [--coverage]");
+    println!("      simulates the latch toggles of a test vector file and reports when an assert or the given lights turn on");
+    println!("      --coverage lists the groups that never toggled afterwards");
+    println!("  faults <file.txt> [--ticks n]");
+    println!("      injects every stuck-at-0 and stuck-at-1 fault in turn while replaying a test vector file");
+    println!("      and lists the faults that never change a light, display, framebuffer or assert");
+    println!("  critical-path");
+    println!("      prints the longest path of gates between latches, clocks and framebuffers in ticks of unit delay mode");
+    println!("  stats");
+    println!("      prints cell and group counts per component, fan-in and fan-out, wire nets and how much of the canvas is used");
+    println!("  import <file.png|file.bmp|file.ppm> [--scale n]");
+    println!("      replaces the canvas with an image, every n x n block of pixels becomes a cell of the component with the nearest colour");
+}
+
+pub fn run_command(args: &[String]){
+    match args[0].as_str() {
+        "export" => export_command(args),
+        "import" => import_command(args),
+        "record" => record_command(args),
+        "truth-table" => truth_table_command(args),
+        "equivalence" => equivalence_command(args),
+        "bmc" => bmc_command(args),
+        "replay" => replay_command(args),
+        "faults" => faults_command(args),
+        "critical-path" => critical_path_command(args),
+        "stats" => stats_command(args),
+        _ => print_usage()
+    }
+}
+
+fn export_command(args: &[String]){
+    let Some(path) = args.get(1).filter(|arg| !arg.starts_with("--")) else {
+        print_usage();
+        return;
+    };
+    let mut component_data = load_canvas(args);
+    let sim_view = args.iter().any(|arg| arg == "--sim");
+    if sim_view {
+        compile_canvas(&mut component_data);
+        for _ in 0..get_number_option(args, "--ticks", 0) {
+            component_data.update_canvas();
+        }
+    }
+    let region = get_region_option(args).unwrap_or(export::whole_canvas());
+    let renderer = export::render_region(&mut component_data, region, sim_view, std::cmp::max(get_number_option(args, "--scale", 2), 1));
+    match export::save_image(std::path::Path::new(path), &renderer) {
+        Ok(_) => println!("saved {}", path),
+        Err(error) => println!("couldn't save {}: {}", path, error)
+    }
+}
+
+fn import_command(args: &[String]){
+    let Some(path) = args.get(1).filter(|arg| !arg.starts_with("--")) else {
+        print_usage();
+        return;
+    };
+    let image = match import::load_image(std::path::Path::new(path)) {
+        Ok(image) => image,
+        Err(error) => {
+            println!("couldn't load {}: {}", path, error);
+            return;
+        }
+    };
+    //the cells are replaced, labels and delays of the canvas are kept
+    let mut component_data = load_canvas(args);
+    for column in component_data.array.iter_mut(){
+        for element in column.iter_mut(){
+            element.component_type = ComponentType::NOTHING;
+        }
+    }
+    let report = import::image_to_canvas(&image, std::cmp::max(get_number_option(args, "--scale", 2), 1), &mut component_data);
+    if report.cropped {
+        println!("the image is bigger than the canvas, only the top left part was imported");
+    }
+    if !report.unknown_colors.is_empty() {
+        println!("{} colours aren't component colours and were mapped to the nearest component:", report.unknown_colors.len());
+        for (color, count) in report.unknown_colors.iter().take(20) {
+            println!("  ({}, {}, {}) in {} cells", color.0, color.1, color.2, count);
+        }
+        if report.unknown_colors.len() > 20 {
+            println!("  ...");
+        }
+    }
+    let canvas_path = get_canvas_path(args);
+    save_array(&component_data, &canvas_path);
+    println!("imported {} cells into {}", report.cells, canvas_path.display());
+}
+
+fn record_command(args: &[String]){
+    let Some(path) = args.get(1).filter(|arg| !arg.starts_with("--")) else {
+        print_usage();
+        return;
+    };
+    let mut component_data = load_canvas(args);
+    compile_canvas(&mut component_data);
+    let region = get_region_option(args).unwrap_or(export::whole_canvas());
+    let scale = std::cmp::max(get_number_option(args, "--scale", 2), 1);
+    let every = std::cmp::max(get_number_option(args, "--every", 1), 1);
+    let vectors = get_vectors_option(args).unwrap_or_default();
+    let mut recorder = export::GifRecorder::new((region.1.0 - region.0.0 + 1) as u32 * scale, (region.1.1 - region.0.1 + 1) as u32 * scale);
+    for tick in 0..=get_number_option(args, "--ticks", 100) {
+        if let Err(error) = vectors.apply(&mut component_data) {
+            println!("{}", error);
+        }
+        if tick % every == 0 {
+            recorder.add_frame(&export::render_region(&mut component_data, region, true, scale));
+        }
+        component_data.update_canvas();
+    }
+    match recorder.save(std::path::Path::new(path), get_number_option(args, "--delay", 100)) {
+        Ok(_) => println!("saved {} frames to {}", recorder.frame_count(), path),
+        Err(error) => println!("couldn't save {}: {}", path, error)
+    }
+}
+
+fn truth_table_command(args: &[String]){
+    let component_data = load_canvas(args);
+    let region = get_region_option(args).unwrap_or(export::whole_canvas());
+    let table = match truth_table::generate(&component_data, region) {
+        Ok(table) => table,
+        Err(error) => {
+            println!("couldn't make a truth table: {}", error);
+            return;
+        }
+    };
+    match args.get(1).filter(|arg| !arg.starts_with("--")) {
+        Some(path) => match table.save(std::path::Path::new(path)) {
+            Ok(_) => println!("saved {} rows to {}", table.rows.len(), path),
+            Err(error) => println!("couldn't save {}: {}", path, error)
+        },
+        None => print!("{}", table.to_markdown())
+    }
+}
+
+fn format_bit(bit: bool) -> &'static str {
+    if bit { "1" } else { "0" }
+}
+
+fn equivalence_command(args: &[String]){
+    let component_data = load_canvas(args);
+    let region = get_region_option(args).unwrap_or(export::whole_canvas());
+    let other_data = match args.get(1).filter(|arg| !arg.starts_with("--")) {
+        Some(path) => {
+            let mut other_data = ComponentData::default();
+            load_array(&mut other_data, std::path::Path::new(path));
+            other_data
+        }
+        None => load_canvas(args)
+    };
+    let other_region = get_named_region_option(args, "--other-region").unwrap_or(export::whole_canvas());
+    let force_sat = args.iter().any(|arg| arg == "--sat");
+    let equivalence = match equivalence::check((&component_data, region), (&other_data, other_region), force_sat) {
+        Ok(equivalence) => equivalence,
+        Err(error) => {
+            println!("couldn't check equivalence: {}", error);
+            return;
+        }
+    };
+    let method = match equivalence.method {
+        equivalence::Method::Exhaustive => "every input combination",
+        equivalence::Method::Sat => "the sat solver"
+    };
+    for (a, b) in equivalence.inputs.iter(){
+        println!("input  {} = {}", a, b);
+    }
+    for (a, b) in equivalence.outputs.iter(){
+        println!("output {} = {}", a, b);
+    }
+    let Some(counterexample) = equivalence.counterexample else {
+        println!("equivalent, checked with {}", method);
+        return;
+    };
+    println!("not equivalent, found with {}. counterexample:", method);
+    for ((name, _), bit) in equivalence.inputs.iter().zip(counterexample.inputs.iter()){
+        println!("  {} = {}", name, format_bit(*bit));
+    }
+    for (i, (a, b)) in equivalence.outputs.iter().enumerate(){
+        let (bit_a, bit_b) = (counterexample.outputs_a[i], counterexample.outputs_b[i]);
+        println!("  {} = {}, {} = {}{}", a, format_bit(bit_a), b, format_bit(bit_b), if bit_a != bit_b { "  <- differs" } else { "" });
+    }
+}
+
+fn bmc_command(args: &[String]){
+    let mut component_data = load_canvas(args);
+    compile_canvas(&mut component_data);
+    let properties = match bmc::find_properties(&component_data, &get_property_options(args)) {
+        Ok(properties) => properties,
+        Err(error) => {
+            println!("couldn't check: {}", error);
+            return;
+        }
+    };
+    let ticks = get_number_option(args, "--ticks", bmc::DEFAULT_TICKS);
+    let violation = match bmc::check(&component_data, &properties, ticks) {
+        Ok(Some(violation)) => violation,
+        Ok(None) => {
+            println!("none of the {} checked groups can turn on within {} ticks", properties.len(), ticks);
+            return;
+        }
+        Err(error) => {
+            println!("couldn't check: {}", error);
+            return;
+        }
+    };
+    let summary = format!("{} turns on in tick {}", describe_group(&component_data, violation.group), violation.tick);
+    if violation.vectors.toggles.is_empty() {
+        println!("{} without toggling any latch", summary);
+    } else {
+        println!("{} with these latch toggles:", summary);
+        print!("{}", violation.vectors.to_text());
+    }
+    if let Some(path) = args.get(1).filter(|arg| !arg.starts_with("--")) {
+        match violation.vectors.save(std::path::Path::new(path), &summary) {
+            Ok(_) => println!("saved {}", path),
+            Err(error) => println!("couldn't save {}: {}", path, error)
+        }
+    }
+}
+
+fn replay_command(args: &[String]){
+    let Some(path) = args.get(1).filter(|arg| !arg.starts_with("--")) else {
+        print_usage();
+        return;
+    };
+    let vectors = match TestVectors::load(std::path::Path::new(path)) {
+        Ok(vectors) => vectors,
+        Err(error) => {
+            println!("couldn't load {}: {}", path, error);
+            return;
+        }
+    };
+    let mut component_data = load_canvas(args);
+    compile_canvas(&mut component_data);
+    let properties = match bmc::find_properties(&component_data, &get_property_options(args)) {
+        Ok(properties) => properties,
+        Err(error) => {
+            println!("{}", error);
+            vec![]
+        }
+    };
+    let ticks = get_number_option(args, "--ticks", vectors.last_tick().map_or(100, |tick| tick as u32 + 100));
+    let mut was_on = vec![false; properties.len()];
+    for _ in 0..ticks {
+        let tick = component_data.ticks;
+        if let Err(error) = vectors.apply(&mut component_data) {
+            println!("{}", error);
+            return;
+        }
+        component_data.update_canvas();
+        for (index, group) in properties.iter().enumerate(){
+            let is_on = component_data.is_group_enabled(*group);
+            if is_on != was_on[index] {
+                println!("tick {}: {} turned {}", tick, describe_group(&component_data, *group), if is_on { "on" } else { "off" });
+                was_on[index] = is_on;
+            }
+        }
+    }
+    println!("simulated {} ticks", ticks);
+    if args.iter().any(|arg| arg == "--coverage") {
+        print!("{}", coverage::collect(&component_data).report(&component_data));
+    }
+}
+
+fn faults_command(args: &[String]){
+    let Some(path) = args.get(1).filter(|arg| !arg.starts_with("--")) else {
+        print_usage();
+        return;
+    };
+    let vectors = match TestVectors::load(std::path::Path::new(path)) {
+        Ok(vectors) => vectors,
+        Err(error) => {
+            println!("couldn't load {}: {}", path, error);
+            return;
+        }
+    };
+    let mut component_data = load_canvas(args);
+    if component_data.optimize {
+        println!("faults can't be injected into merged groups, the optimization pass is turned off");
+        component_data.set_optimize(false);
+    }
+    compile_canvas(&mut component_data);
+    let ticks = get_number_option(args, "--ticks", vectors.last_tick().map_or(100, |tick| tick as u32 + 100));
+    let fault_list = faults::list_faults(&component_data);
+    match faults::simulate(&component_data, fault_list, &vectors, ticks as u64) {
+        Ok(report) => print!("{}", report.report(&component_data)),
+        Err(error) => println!("couldn't simulate faults: {}", error)
+    }
+}
+
+fn critical_path_command(args: &[String]){
+    let mut component_data = load_canvas(args);
+    compile_canvas(&mut component_data);
+    match critical_path::find(&component_data) {
+        Some(path) => print!("{}", path.report(&component_data)),
+        None => println!("there is no path between latches, clocks or framebuffers")
+    }
+}
+
+fn stats_command(args: &[String]){
+    let mut component_data = load_canvas(args);
+    compile_canvas(&mut component_data);
+    print!("{}", stats::collect(&component_data).report(&component_data));
+}
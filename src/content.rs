@@ -1,10 +1,17 @@
+use crate::analyzer::Analyzer;
+use crate::breakpoints::{Breakpoint, Condition};
+use crate::renderer::CellImage;
+use crate::coverage::heat_color;
+use crate::program::{Instruction, Opcode, OptimizationReport, Program, PHASES};
+use crate::region::describe_group;
+
 pub const WIDTH: u32 = 700;
 pub const HEIGHT: u32 = 350;
 pub const SIZE: i32 = 0;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, PartialEq)]
-pub enum ComponentType {NOTHING, WRITE_TO_WIRE, WIRE, CROSS, READ_FROM_WIRE, AND, OR, XOR, NOT, NAND, XNOR, COMMENT, CLOCK, LATCH, LIGHT, NUM_COMPONENTS}
+pub enum ComponentType {NOTHING, WRITE_TO_WIRE, WIRE, CROSS, READ_FROM_WIRE, AND, OR, XOR, NOT, NAND, XNOR, COMMENT, CLOCK, LATCH, LIGHT, SEVEN_SEGMENT, HEX_DISPLAY, FRAMEBUFFER, ASSERT, NUM_COMPONENTS}
 
 impl ComponentType{
     pub fn from_u32(val: u32) -> ComponentType{
@@ -23,12 +30,27 @@ impl ComponentType{
             12 => ComponentType::CLOCK,
             13 => ComponentType::LATCH,
             14 => ComponentType::LIGHT,
+            15 => ComponentType::SEVEN_SEGMENT,
+            16 => ComponentType::HEX_DISPLAY,
+            17 => ComponentType::FRAMEBUFFER,
+            18 => ComponentType::ASSERT,
             _ => ComponentType::NOTHING,
         }
     }
+
+    pub fn is_logic_gate(self) -> bool {
+        self as u32 >= ComponentType::AND as u32 && self as u32 <= ComponentType::ASSERT as u32
+    }
+
+    pub fn is_display(self) -> bool {
+        self == ComponentType::SEVEN_SEGMENT || self == ComponentType::HEX_DISPLAY
+    }
 }
 
-pub const COLORS: [((u8, u8, u8), (u8, u8, u8)); 15] =//dark wires
+//segments a to g, bit 0 is segment a
+pub const HEX_SEGMENTS: [u8; 16] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79, 0x71];
+
+pub const COLORS: [((u8, u8, u8), (u8, u8, u8)); 19] =//dark wires
     [((031, 037, 049), (031, 037, 049)),
         ((085, 062, 071), (255, 113, 113)),
         ((099, 097, 079), (177, 177, 051)),//((099, 097, 079), (251, 251, 074)),
@@ -43,9 +65,13 @@ pub const COLORS: [((u8, u8, u8), (u8, u8, u8)); 15] =//dark wires
         ((067, 072, 079), (067, 072, 079)),
         ((085, 040, 069), (255, 000, 078)),
         ((061, 085, 081), (110, 251, 183)),
-        ((100, 100, 100), (255, 255, 255))];
+        ((100, 100, 100), (255, 255, 255)),
+        ((60, 30, 30), (255, 60, 40)),
+        ((30, 55, 35), (80, 255, 110)),
+        ((20, 24, 32), (230, 240, 255)),
+        ((95, 45, 20), (255, 110, 0))];
 
-pub const NAMES: [&str; 15] =  [
+pub const NAMES: [&str; 19] =  [
     "nothing",
     "writer",
     "wire",
@@ -60,20 +86,59 @@ pub const NAMES: [&str; 15] =  [
     "comment",
     "clock",
     "latch",
-    "light"
+    "light",
+    "seven segment",
+    "hex display",
+    "framebuffer",
+    "assert"
 ];
 
+pub const FRAMEBUFFER_MAX_ADDRESS_BITS: usize = 7;
+
+//ticks a group takes to follow its inputs in timing mode, for clocks it's half the period
+//readers, writers and wires pass signals on in the same tick so gates alone set the timing, like in unit delay mode
+pub const DEFAULT_DELAYS: [u32; 19] = [0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1];
+pub const MAX_DELAY: u32 = 1000;
+
+//changes through groups with delay 0 are followed this many times per tick, so a loop of them can't hang the simulation
+const MAX_DELTA_CYCLES: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DelayMode {Unit, Zero, Timed}
+
+impl DelayMode {
+    pub fn next(self) -> DelayMode {
+        match self {
+            DelayMode::Unit => DelayMode::Zero,
+            DelayMode::Zero => DelayMode::Timed,
+            DelayMode::Timed => DelayMode::Unit,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DelayMode::Unit => "UNIT DELAY",
+            DelayMode::Zero => "ZERO DELAY",
+            DelayMode::Timed => "TIMED",
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Component{
     pub component_type: ComponentType,
     pub belongs_to: i32,
 }
 
+#[derive(Clone)]
+pub struct Label{
+    pub position: (i32, i32),
+    pub text: String,
+}
+
 pub struct MiscData{
     pub selected_type: ComponentType,
     pub run_sim: bool,
-    pub stopwatch: stopwatch::Stopwatch,
-    pub last_time: i64,
     pub last_mouse_pos: (i32, i32),
     pub mouse_pos_on_middle_press: (i32, i32),
     pub shift_pressed: bool,
@@ -82,7 +147,14 @@ pub struct MiscData{
     pub paste: (bool, (i32, i32)),
     pub selection: ((i32, i32), (i32, i32)),
     pub copied_data: Vec<Vec<u8>>,
-    pub selected_mspt: u8
+    pub selected_mspt: u8,
+    pub editing_label: Option<usize>,
+    pub skip_text_input: bool,
+    pub critical_path: Vec<usize>,
+    pub analyzer_zoom: f32,
+    pub analyzer_cursor: Option<u64>,
+    pub breakpoint_value: Option<String>,
+    pub breakpoint_trigger: Vec<usize>
 }
 
 impl MiscData{
@@ -90,8 +162,6 @@ impl MiscData{
         Self{
             selected_type: ComponentType::WIRE,
             run_sim: false,
-            stopwatch: stopwatch::Stopwatch::start_new(),
-            last_time: 0,
             last_mouse_pos: (0, 0),
             mouse_pos_on_middle_press: (0, 0),
             shift_pressed: false,
@@ -100,29 +170,54 @@ impl MiscData{
             paste: (false, (0, 0)),
             selection: ((0, 0), (0, 0)),
             copied_data: vec![],
-            selected_mspt: 4
+            selected_mspt: 4,
+            editing_label: None,
+            skip_text_input: false,
+            critical_path: vec![],
+            analyzer_zoom: 4.0,
+            analyzer_cursor: None,
+            breakpoint_value: None,
+            breakpoint_trigger: vec![]
         }
     }
 }
 
+//every bit of enabled is a separate copy of the circuit, the editor shows lane 0
+pub const LANES: usize = 64;
+
+#[derive(Clone)]
 pub(crate) struct LogicComponent{
-    pub enabled: bool,
-    pub to_update: bool,
     pub component_type: ComponentType,
     pub elements: Vec<(usize, usize)>,
     pub component_before: Vec<u32>,
-    pub component_after: Vec<u32>
+    pub component_after: Vec<u32>,
+    pub memory: Vec<u64>,
+    pub memory_size: (usize, usize),
+    pub delay: u32,
+}
+
+impl LogicComponent {
+    pub fn bounding_box(&self) -> ((usize, usize), (usize, usize)){
+        let mut min = self.elements[0];
+        let mut max = self.elements[0];
+        for element in self.elements.iter(){
+            min = (std::cmp::min(min.0, element.0), std::cmp::min(min.1, element.1));
+            max = (std::cmp::max(max.0, element.0), std::cmp::max(max.1, element.1));
+        }
+        (min, max)
+    }
 }
 
 impl Default for LogicComponent {
     fn default() -> Self {
         Self{
-            enabled: false,
-            to_update: true,
             component_type: ComponentType::NOTHING,
             elements: vec![],
             component_before: vec![],
             component_after: vec![],
+            memory: vec![],
+            memory_size: (0, 0),
+            delay: 0,
         }
     }
 }
@@ -132,6 +227,20 @@ pub struct ComponentData{
     pub array: Vec<[Component; HEIGHT as usize]>,
     pub to_update: Vec<(usize, usize)>,
     pub(crate) logic_components: Vec<LogicComponent>,
+    pub(crate) program: Program,
+    pub labels: Vec<Label>,
+    pub delays: Vec<((usize, usize), u32)>,
+    pub ticks: u64,
+    pub(crate) cell_image: CellImage,
+    pub(crate) cell_image_sim_view: Option<bool>,
+    pub(crate) changed_groups: Vec<u32>,
+    pub(crate) delay_mode: DelayMode,
+    pub(crate) optimize: bool,
+    pub(crate) optimization: Option<OptimizationReport>,
+    pub(crate) heatmap: bool,
+    pub(crate) analyzer: Analyzer,
+    pub(crate) breakpoints: Vec<Breakpoint>,
+    pub(crate) breakpoint_hit: Option<usize>,
     pub position_on_screen: (f32, f32),
     pub zoom: f32,
 }
@@ -142,6 +251,20 @@ impl ComponentData{
             array: vec![[Component{component_type: ComponentType::NOTHING, belongs_to: -1}; HEIGHT as usize]; WIDTH as usize],
             to_update: vec![],
             logic_components: vec![],
+            program: Program::default(),
+            labels: vec![],
+            delays: vec![],
+            ticks: 0,
+            cell_image: CellImage::new(WIDTH, HEIGHT),
+            cell_image_sim_view: None,
+            changed_groups: vec![],
+            delay_mode: DelayMode::Unit,
+            optimize: false,
+            optimization: None,
+            heatmap: false,
+            analyzer: Analyzer::default(),
+            breakpoints: vec![],
+            breakpoint_hit: None,
             position_on_screen: (0.0, 0.0),
             zoom: 1.0
         }
@@ -162,6 +285,175 @@ impl ComponentData{
                 }
             }
         }
+        self.sort_inputs();
+        self.allocate_framebuffers();
+        self.assign_delays();
+        self.program = Program::compile(&self.logic_components);
+        self.optimization = None;
+        if self.optimize {
+            self.apply_optimization();
+        }
+        self.cell_image_sim_view = None;
+    }
+
+    //runs the optimization pass or undoes it on the compiled program, groups that look different afterwards are repainted
+    fn apply_optimization(&mut self){
+        let before: Vec<u64> = (0..self.logic_components.len()).map(|group| self.program.value(group)).collect();
+        if self.optimize {
+            self.optimization = Some(self.program.optimize(&self.logic_components, self.delay_mode));
+        } else {
+            self.program.rebuild(&self.logic_components);
+            self.optimization = None;
+        }
+        for (group, value) in before.into_iter().enumerate(){
+            if self.program.value(group) != value {
+                self.mark_changed(group);
+            }
+        }
+    }
+
+    pub(crate) fn set_optimize(&mut self, optimize: bool){
+        self.optimize = optimize;
+        if !self.logic_components.is_empty() {
+            self.apply_optimization();
+        }
+    }
+
+    //inputs of displays and framebuffers are ordered by the position of the first element of the input group (column first),
+    //that's what their segments, bits and addresses are read by. other groups keep the order they were linked in
+    fn sort_inputs(&mut self){
+        for i in 0..self.logic_components.len(){
+            if !matches!(self.logic_components[i].component_type, ComponentType::SEVEN_SEGMENT | ComponentType::HEX_DISPLAY | ComponentType::FRAMEBUFFER) {
+                continue;
+            }
+            let mut before = std::mem::take(&mut self.logic_components[i].component_before);
+            before.sort_by_key(|index| self.logic_components[*index as usize].elements[0]);
+            self.logic_components[i].component_before = before;
+        }
+    }
+
+    //framebuffer inputs: column address bits, row address bits, pixel data, write strobe
+    //address bits are split between columns and rows so the pixels come out as square as the framebuffer's shape allows,
+    //a square framebuffer gets the same number of both and columns get the extra one
+    pub(crate) fn framebuffer_layout(&self, gate_index: usize) -> (usize, usize) {
+        let logic_component = &self.logic_components[gate_index];
+        let address_bits = std::cmp::min(logic_component.component_before.len().saturating_sub(2), FRAMEBUFFER_MAX_ADDRESS_BITS * 2);
+        let (min, max) = logic_component.bounding_box();
+        let shape = ((max.0 - min.0 + 1) as f32 / (max.1 - min.1 + 1) as f32).log2();
+        let mut layout = (0, 0);
+        let mut best = f32::MAX;
+        for column_bits in address_bits.saturating_sub(FRAMEBUFFER_MAX_ADDRESS_BITS)..=std::cmp::min(address_bits, FRAMEBUFFER_MAX_ADDRESS_BITS) {
+            let row_bits = address_bits - column_bits;
+            let difference = (shape - (column_bits as f32 - row_bits as f32)).abs();
+            if difference <= best {
+                layout = (column_bits, row_bits);
+                best = difference;
+            }
+        }
+        layout
+    }
+
+    //delays set on a group are kept at one of its cells so they survive recompiling and saving
+    fn assign_delays(&mut self){
+        for logic_component in self.logic_components.iter_mut(){
+            logic_component.delay = DEFAULT_DELAYS[logic_component.component_type as usize];
+        }
+        for (position, delay) in self.delays.iter(){
+            let group = self.array[position.0][position.1].belongs_to;
+            if group != -1 {
+                self.logic_components[group as usize].delay = *delay;
+            }
+        }
+    }
+
+    pub(crate) fn set_group_delay(&mut self, group: usize, delay: u32){
+        let delay = std::cmp::min(delay, MAX_DELAY);
+        let array = &self.array;
+        self.delays.retain(|(position, _)| array[position.0][position.1].belongs_to != group as i32);
+        if delay != DEFAULT_DELAYS[self.logic_components[group].component_type as usize] {
+            self.delays.push((self.logic_components[group].elements[0], delay));
+        }
+        self.logic_components[group].delay = delay;
+        self.program.delays[group] = delay;
+        if self.optimization.is_some() && self.delay_mode == DelayMode::Timed {
+            self.apply_optimization();
+        }
+    }
+
+    //pending events are dropped and the groups they were for are evaluated again, clocks have to keep running in every mode
+    pub(crate) fn set_delay_mode(&mut self, delay_mode: DelayMode){
+        self.delay_mode = delay_mode;
+        for event in std::mem::take(&mut self.program.events).into_iter(){
+            let group = event.0.group as usize;
+            self.program.dirty[group] |= event.0.value ^ self.program.state[group];
+        }
+        self.program.projected = self.program.state.clone();
+        for instruction in self.program.levelized.iter(){
+            if instruction.opcode == Opcode::Clock {
+                self.program.dirty[instruction.group as usize] = !0;
+            }
+        }
+        //what can be merged depends on the mode
+        if self.optimization.is_some() {
+            self.apply_optimization();
+        }
+    }
+
+    fn allocate_framebuffers(&mut self){
+        for i in 0..self.logic_components.len(){
+            if self.logic_components[i].component_type == ComponentType::FRAMEBUFFER {
+                let (column_bits, row_bits) = self.framebuffer_layout(i);
+                self.logic_components[i].memory_size = (1 << column_bits, 1 << row_bits);
+                self.logic_components[i].memory = vec![0; (1 << column_bits) * (1 << row_bits)];
+            }
+        }
+    }
+
+    fn read_inputs_as_number(&self, gate_index: usize, first: usize, count: usize, lane: usize) -> usize {
+        let mut value = 0;
+        for bit in 0..count {
+            if self.program.value(self.logic_components[gate_index].component_before[first + bit] as usize) >> lane & 1 != 0{
+                value |= 1 << bit;
+            }
+        }
+        value
+    }
+
+    //inputs a display has no use for, segments after g, bits after the fourth or address bits past the layout of a framebuffer
+    pub(crate) fn ignored_inputs(&self, group: usize) -> &[u32] {
+        let inputs = &self.logic_components[group].component_before;
+        let used = match self.logic_components[group].component_type {
+            ComponentType::SEVEN_SEGMENT => 7,
+            ComponentType::HEX_DISPLAY => 4,
+            ComponentType::FRAMEBUFFER => {
+                let (column_bits, row_bits) = self.framebuffer_layout(group);
+                return &inputs[std::cmp::min(column_bits + row_bits, inputs.len())..inputs.len().saturating_sub(2)];
+            }
+            _ => return &[]
+        };
+        &inputs[std::cmp::min(used, inputs.len())..]
+    }
+
+    //one line for every group with ignored inputs, printed after compiling
+    pub(crate) fn ignored_input_warnings(&self) -> Vec<String> {
+        (0..self.logic_components.len()).filter(|group| !self.ignored_inputs(*group).is_empty()).map(|group| {
+            format!("{} ignores {} of its {} inputs", describe_group(self, group), self.ignored_inputs(group).len(), self.logic_components[group].component_before.len())
+        }).collect()
+    }
+
+    pub(crate) fn display_segments(&self, gate_index: usize) -> u8 {
+        let inputs = &self.logic_components[gate_index].component_before;
+        let mut value = 0;
+        for (i, input) in inputs.iter().enumerate(){
+            if self.is_group_enabled(*input as usize){
+                value |= 1 << i;
+            }
+        }
+        match self.logic_components[gate_index].component_type {
+            ComponentType::SEVEN_SEGMENT => (value & 0x7F) as u8,
+            ComponentType::HEX_DISPLAY => HEX_SEGMENTS[(value & 0xF) as usize],
+            _ => 0
+        }
     }
 
     pub(crate) fn clear_compiled_data(&mut self){
@@ -171,134 +463,316 @@ impl ComponentData{
             }
         }
         self.logic_components.clear();
+        self.program = Program::default();
+        self.changed_groups.clear();
+        self.cell_image_sim_view = None;
+        self.analyzer = Analyzer::default();
+        self.breakpoints.clear();
+        self.breakpoint_hit = None;
+        self.ticks = 0;
     }
 
-    pub fn translate_mouse_pos(&self, mouse_x: f32, mouse_y: f32) -> (i32, i32){
-        (((mouse_x - self.position_on_screen.0) / self.zoom - 0.5).round() as i32, ((mouse_y - self.position_on_screen.1) / self.zoom - 0.5).round() as i32)
+    //starts the simulation over on the compiled program: tick 0, everything off, nothing forced and empty framebuffers
+    pub(crate) fn reset_simulation(&mut self){
+        self.program.reset();
+        for logic_component in self.logic_components.iter_mut(){
+            logic_component.memory.iter_mut().for_each(|word| *word = 0);
+        }
+        self.changed_groups.clear();
+        self.cell_image_sim_view = None;
+        self.ticks = 0;
+        if self.optimize {
+            self.apply_optimization();
+        }
     }
 
-    pub fn update_canvas(&mut self){
-        self.update_component(ComponentType::READ_FROM_WIRE);//add to func: to_update = false
-        self.update_component(ComponentType::NOTHING);
-        self.update_component(ComponentType::WRITE_TO_WIRE);//add to func: to_update = false
-        self.update_component(ComponentType::WIRE);//add to func: to_update = false
+    pub fn set_cell(&mut self, x: usize, y: usize, component_type: ComponentType){
+        self.array[x][y].component_type = component_type;
+        if self.cell_image_sim_view == Some(false) {
+            self.cell_image.set_pixel(x as u32, y as u32, COLORS[component_type as usize].0);
+        }
     }
 
-    fn update_component(&mut self, component_type: ComponentType){
-        for i in 0..self.logic_components.len(){
-            if !((component_type == ComponentType::NOTHING && //this and next line: is logic gate
-                self.logic_components[i].component_type as u32 >= ComponentType::AND as u32 && self.logic_components[i].component_type as u32 <= ComponentType::LIGHT as u32) ||
-                (self.logic_components[i].component_type == component_type)) ||
-                !self.logic_components[i].to_update{
-                continue;
+    //repaints the cells that changed since the last call, everything if the view changed
+    pub(crate) fn update_cell_image(&mut self, sim_view: bool){
+        if self.cell_image_sim_view != Some(sim_view) {
+            self.cell_image_sim_view = Some(sim_view);
+            self.changed_groups.clear();
+            self.program.changed.iter_mut().for_each(|changed| *changed = false);
+            for i in 0..WIDTH as usize{
+                for j in 0..HEIGHT as usize{
+                    let color = if sim_view && self.array[i][j].belongs_to != -1 {
+                        self.group_color(self.array[i][j].belongs_to as usize)
+                    } else if sim_view {
+                        COLORS[0].0
+                    } else {
+                        COLORS[self.array[i][j].component_type as usize].0
+                    };
+                    self.cell_image.set_pixel(i as u32, j as u32, color);
+                }
             }
-            let previous_state = self.logic_components[i].enabled;
-            let mut should_turn_on = false;
-
-            match self.logic_components[i].component_type {
-                ComponentType::OR    => { should_turn_on = self.should_or_turn_on   (i); }
-                ComponentType::AND   => { should_turn_on = self.should_and_turn_on  (i); }
-                ComponentType::XOR   => { should_turn_on = self.should_xor_turn_on  (i); }
-                ComponentType::NOT   => { should_turn_on = self.should_not_turn_on  (i); }
-                ComponentType::NAND  => { should_turn_on = self.should_nand_turn_on (i); }
-                ComponentType::XNOR  => { should_turn_on = self.should_xnor_turn_on (i); }
-                ComponentType::CLOCK => { should_turn_on = self.should_clock_turn_on(i); }
-                ComponentType::LATCH => { should_turn_on = self.should_latch_turn_on(i); }
-                ComponentType::LIGHT => { should_turn_on = self.should_or_turn_on   (i); }
-                ComponentType::COMMENT => {}
-                _ => {
-                    for j in 0..self.logic_components[i].component_before.len(){
-                        should_turn_on = should_turn_on || self.logic_components[self.logic_components[i].component_before[j] as usize].enabled;
-                    }
+            return;
+        }
+        if sim_view {
+            for group in std::mem::take(&mut self.changed_groups){
+                self.program.changed[group as usize] = false;
+                let color = self.group_color(group as usize);
+                for element in self.logic_components[group as usize].elements.iter(){
+                    self.cell_image.set_pixel(element.0 as u32, element.1 as u32, color);
                 }
             }
+        }
+    }
 
-            if previous_state != should_turn_on{
-                self.logic_components[i].enabled = should_turn_on;
-                for j in 0..self.logic_components[i].component_after.len(){
-                    let index = self.logic_components[i].component_after[j] as usize;
-                    self.logic_components[index].to_update = true;
+    //copies what drawing needs into the render loop's copy, so the frame is drawn without holding the lock.
+    //the simulation only changes group states, toggle counts, framebuffer memory and probes, everything else
+    //changes with edits and is only copied when the frame edited something
+    pub(crate) fn sync_view(&mut self, view: &mut ComponentData, edited: bool){
+        if edited {
+            for x in 0..WIDTH as usize{
+                for y in 0..HEIGHT as usize{
+                    if view.array[x][y].component_type != self.array[x][y].component_type {
+                        view.set_cell(x, y, self.array[x][y].component_type);
+                    }
+                }
+            }
+            view.array.copy_from_slice(&self.array);
+            if view.heatmap != self.heatmap {
+                view.cell_image_sim_view = None;
+            }
+            view.changed_groups.clear();
+            view.logic_components.clone_from(&self.logic_components);
+            view.program.alias.clone_from(&self.program.alias);
+            view.program.changed = vec![false; self.program.changed.len()];
+            view.labels.clone_from(&self.labels);
+            view.delays.clone_from(&self.delays);
+            view.delay_mode = self.delay_mode;
+            view.optimize = self.optimize;
+            view.heatmap = self.heatmap;
+            view.breakpoints.clone_from(&self.breakpoints);
+            view.analyzer.clone_from(&self.analyzer);
+        } else {
+            for (group, logic_component) in self.logic_components.iter().enumerate(){
+                if logic_component.component_type == ComponentType::FRAMEBUFFER {
+                    view.logic_components[group].memory.copy_from_slice(&logic_component.memory);
                 }
             }
-            if self.logic_components[i].component_type != ComponentType::CLOCK{
-                self.logic_components[i].to_update = false;
+            for (view_probe, probe) in view.analyzer.probes.iter_mut().zip(self.analyzer.probes.iter()){
+                view_probe.follow(probe);
             }
         }
+        view.program.state.clone_from(&self.program.state);
+        view.program.toggles.clone_from(&self.program.toggles);
+        view.program.force_mask.clone_from(&self.program.force_mask);
+        view.program.force_value.clone_from(&self.program.force_value);
+        view.ticks = self.ticks;
+        view.position_on_screen = self.position_on_screen;
+        view.zoom = self.zoom;
+        for group in std::mem::take(&mut self.changed_groups){
+            self.program.changed[group as usize] = false;
+            view.changed_groups.push(group);
+        }
     }
 
-    fn should_not_turn_on  (&self, gate_index: usize) -> bool {
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                return false;
-            }
+    //the heatmap colours groups by how often they toggled instead of on and off
+    fn group_color(&self, group: usize) -> (u8, u8, u8) {
+        if self.heatmap {
+            return heat_color(self.program.toggles[group]);
         }
-        return true;
+        let component_type = self.logic_components[group].component_type;
+        if self.is_group_enabled(group) { COLORS[component_type as usize].1 } else { COLORS[component_type as usize].0 }
     }
 
-    fn should_or_turn_on   (&self, gate_index: usize) -> bool {
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                return true;
+    pub(crate) fn set_heatmap(&mut self, heatmap: bool){
+        self.heatmap = heatmap;
+        self.cell_image_sim_view = None;
+    }
+
+    pub fn translate_mouse_pos(&self, mouse_x: f32, mouse_y: f32) -> (i32, i32){
+        (((mouse_x - self.position_on_screen.0) / self.zoom - 0.5).round() as i32, ((mouse_y - self.position_on_screen.1) / self.zoom - 0.5).round() as i32)
+    }
+
+    pub(crate) fn is_group_enabled(&self, group: usize) -> bool {
+        self.program.value(group) & 1 != 0
+    }
+
+    //unit delay: a signal gets through one gate (reader, gate, writer and wire) per tick
+    //zero delay: the levelized program settles all combinational logic in one tick
+    //timed: every group follows its inputs after its own delay
+    pub fn update_canvas(&mut self){
+        match self.delay_mode {
+            DelayMode::Unit => {
+                for phase in 0..PHASES {
+                    for i in 0..self.program.phases[phase].len(){
+                        self.execute(self.program.phases[phase][i]);
+                    }
+                }
+            }
+            DelayMode::Zero => {
+                for i in 0..self.program.levelized.len(){
+                    self.execute(self.program.levelized[i]);
+                }
             }
+            DelayMode::Timed => self.update_timed()
+        }
+        self.ticks += 1;
+        if !self.analyzer.probes.is_empty() {
+            self.record_probes();
+        }
+        if !self.breakpoints.is_empty() {
+            self.check_breakpoints();
         }
-        return false;
     }
 
-    fn should_and_turn_on  (&self, gate_index: usize) -> bool {
-        if self.logic_components[gate_index].component_before.len() == 0{
-            return false;
-        }
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if !self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                return false;
+    //the first breakpoint whose condition started to hold in this tick is kept until someone takes it
+    fn check_breakpoints(&mut self){
+        for i in 0..self.breakpoints.len(){
+            let met = self.breakpoints[i].condition.is_met(self);
+            if met && !self.breakpoints[i].met && self.breakpoint_hit.is_none() {
+                self.breakpoint_hit = Some(i);
             }
+            self.breakpoints[i].met = met;
         }
-        return true;
     }
 
-    fn should_nand_turn_on (&self, gate_index: usize) -> bool {
-        if self.logic_components[gate_index].component_before.len() == 0{
-            return true;
+    pub(crate) fn add_breakpoint(&mut self, condition: Condition){
+        let met = condition.is_met(self);
+        self.breakpoints.push(Breakpoint{condition, met});
+    }
+
+    fn record_probes(&mut self){
+        for i in 0..self.analyzer.probes.len(){
+            let value = self.is_group_enabled(self.analyzer.probes[i].group);
+            self.analyzer.probes[i].record(self.ticks, value);
         }
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if !self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                return true;
+    }
+
+    //false if the panel is full
+    pub(crate) fn toggle_probe(&mut self, group: usize) -> bool {
+        let value = self.is_group_enabled(group);
+        self.analyzer.toggle_probe(group, self.ticks, value)
+    }
+
+    //groups whose inputs changed are evaluated at the current tick and their new value is scheduled delay ticks later,
+    //then everything due by the next tick happens, following groups with delay 0 within the tick
+    fn update_timed(&mut self){
+        let now = self.ticks;
+        self.evaluate_timed(now, false);
+        for _ in 0..MAX_DELTA_CYCLES {
+            if !self.program.has_events_until(now + 1) {
+                break;
             }
+            while self.program.has_events_until(now + 1) {
+                let event = self.program.events.pop().unwrap().0;
+                self.apply_event(event.group as usize, event.value);
+            }
+            self.evaluate_timed(now + 1, true);
         }
-        return false;
     }
 
-    fn should_xor_turn_on  (&self, gate_index: usize) -> bool {
-        let mut state = false;
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                state = !state;
+    fn evaluate_timed(&mut self, time: u64, only_zero_delay: bool){
+        for i in 0..self.program.levelized.len(){
+            let instruction = self.program.levelized[i];
+            let group = instruction.group as usize;
+            if self.program.dirty[group] == 0 || (only_zero_delay && self.program.delays[group] != 0) {
+                continue;
+            }
+            let value = if instruction.opcode == Opcode::Framebuffer { self.update_framebuffer(group) } else { self.program.evaluate_from(&instruction, self.program.projected[group]) };
+            self.program.dirty[group] = 0;
+            if value != self.program.projected[group] {
+                self.program.schedule(group, value, time + self.program.delays[group] as u64);
             }
         }
-        state
     }
 
-    fn should_xnor_turn_on (&self, gate_index: usize) -> bool {
-        let mut state = false;
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                state = !state;
+    fn apply_event(&mut self, group: usize, value: u64){
+        let value = self.program.forced(group, value);
+        if self.logic_components[group].component_type == ComponentType::CLOCK {
+            self.program.dirty[group] = !0;
+        }
+        let changed_lanes = self.program.state[group] ^ value;
+        if changed_lanes == 0 {
+            return;
+        }
+        if changed_lanes & 1 != 0 {
+            self.count_toggle(group);
+        }
+        self.program.state[group] = value;
+        self.mark_changed(group);
+        self.program.mark_group_outputs_dirty(group, changed_lanes);
+    }
+
+    //every group is listed once until the cell image is repainted, runs without drawing don't grow the list
+    //groups merged into this one by the optimization pass changed with it
+    fn mark_changed(&mut self, group: usize){
+        if !self.program.changed[group] {
+            self.program.changed[group] = true;
+            self.changed_groups.push(group as u32);
+        }
+        for i in 0..self.program.followers[group].len(){
+            let follower = self.program.followers[group][i] as usize;
+            if !self.program.changed[follower] {
+                self.program.changed[follower] = true;
+                self.changed_groups.push(follower as u32);
             }
         }
-        !state
     }
 
-    fn should_clock_turn_on(&self, gate_index: usize) -> bool {
-        !self.logic_components[gate_index].enabled
+    fn count_toggle(&mut self, group: usize){
+        self.program.toggles[group] = self.program.toggles[group].saturating_add(1);
+        for i in 0..self.program.followers[group].len(){
+            let follower = self.program.followers[group][i] as usize;
+            self.program.toggles[follower] = self.program.toggles[follower].saturating_add(1);
+        }
     }
 
-    fn should_latch_turn_on(&self, gate_index: usize) -> bool {
-        for i in 0..self.logic_components[gate_index].component_before.len(){
-            if self.logic_components[self.logic_components[gate_index].component_before[i] as usize].enabled{
-                return !self.logic_components[gate_index].enabled
+    fn execute(&mut self, instruction: Instruction){
+        let group = instruction.group as usize;
+        if self.program.dirty[group] == 0 {
+            return;
+        }
+        let should_turn_on = if instruction.opcode == Opcode::Framebuffer { self.update_framebuffer(group) } else { self.program.evaluate(&instruction) };
+        let should_turn_on = self.program.forced(group, should_turn_on);
+        let changed_lanes = self.program.state[group] ^ should_turn_on;
+        if changed_lanes != 0 {
+            if changed_lanes & 1 != 0 {
+                self.count_toggle(group);
             }
+            self.program.state[group] = should_turn_on;
+            self.mark_changed(group);
+            self.program.mark_outputs_dirty(&instruction, changed_lanes);
+        }
+        if instruction.opcode != Opcode::Clock {
+            self.program.dirty[group] = 0;
         }
-        self.logic_components[gate_index].enabled
+    }
+
+    //writes the data input while the strobe is on, outputs the pixel at the current address
+    //every lane can address a different pixel so they are handled one by one
+    fn update_framebuffer(&mut self, gate_index: usize) -> u64 {
+        let inputs = self.logic_components[gate_index].component_before.len();
+        if inputs < 2 {
+            return 0;
+        }
+        let (column_bits, row_bits) = self.framebuffer_layout(gate_index);
+        let mut output = 0;
+        for lane in 0..LANES {
+            let column = self.read_inputs_as_number(gate_index, 0, column_bits, lane);
+            let row = self.read_inputs_as_number(gate_index, column_bits, row_bits, lane);
+            let address = row * self.logic_components[gate_index].memory_size.0 + column;
+            let data = self.read_inputs_as_number(gate_index, inputs - 2, 1, lane) == 1;
+            let strobe = self.read_inputs_as_number(gate_index, inputs - 1, 1, lane) == 1;
+            let bit = 1u64 << lane;
+            if strobe {
+                if data {
+                    self.logic_components[gate_index].memory[address] |= bit;
+                } else {
+                    self.logic_components[gate_index].memory[address] &= !bit;
+                }
+            }
+            output |= self.logic_components[gate_index].memory[address] & bit;
+        }
+        output
     }
 
     fn new_logic_component_group(&mut self, x: usize, y: usize){
@@ -344,12 +818,10 @@ impl ComponentData{
                 self.link_component_to_component(x as usize, y as usize, (x + direction.0) as usize, (y + direction.1) as usize);
             }
             if self.array[x as usize][y as usize].component_type == ComponentType::READ_FROM_WIRE &&
-                self.array[(x + direction.0) as usize][(y + direction.1) as usize].component_type as u32 >= ComponentType::AND as u32 &&
-                self.array[(x + direction.0) as usize][(y + direction.1) as usize].component_type as u32 <= ComponentType::LIGHT as u32{
+                self.array[(x + direction.0) as usize][(y + direction.1) as usize].component_type.is_logic_gate(){
                 self.link_component_to_component(x as usize, y as usize, (x + direction.0) as usize, (y + direction.1) as usize);
             }
-            if self.array[x as usize][y as usize].component_type as u32 >= ComponentType::AND as u32 &&
-                self.array[x as usize][y as usize].component_type as u32 <= ComponentType::LIGHT as u32 &&
+            if self.array[x as usize][y as usize].component_type.is_logic_gate() &&
                 self.array[(x + direction.0) as usize][(y + direction.1) as usize].component_type == ComponentType::WRITE_TO_WIRE{
                 self.link_component_to_component(x as usize, y as usize, (x + direction.0) as usize, (y + direction.1) as usize);
             }
@@ -375,12 +847,248 @@ impl ComponentData{
 
     pub(crate) fn click_latch(&mut self, mouse_x: f32, mouse_y: f32){
         let pos = self.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
-        if self.array[pos.0 as usize][pos.1 as usize].component_type== ComponentType::LATCH && self.array[pos.0 as usize][pos.1 as usize].belongs_to != -1 {
-            self.logic_components[self.array[pos.0 as usize][pos.1 as usize].belongs_to as usize].enabled = !self.logic_components[self.array[pos.0 as usize][pos.1 as usize].belongs_to as usize].enabled;
-            for i in 0..self.logic_components[self.array[pos.0 as usize][pos.1 as usize].belongs_to as usize].component_after.len(){
-                let index = *&self.logic_components[self.array[pos.0 as usize][pos.1 as usize].belongs_to as usize].component_after[i] as usize;
-                self.logic_components[index].to_update = true;
+        self.toggle_latch(pos.0 as usize, pos.1 as usize);
+    }
+
+    //false if there is no compiled latch at the position
+    pub(crate) fn toggle_latch(&mut self, x: usize, y: usize) -> bool {
+        if self.array[x][y].component_type == ComponentType::LATCH && self.array[x][y].belongs_to != -1 {
+            let group = self.array[x][y].belongs_to as usize;
+            self.set_group_lanes(group, !self.program.state[group]);
+            return true;
+        }
+        false
+    }
+
+    //sets the state of a group in every lane and wakes up the groups it drives
+    pub(crate) fn set_group_lanes(&mut self, group: usize, lanes: u64){
+        self.program.projected[group] = self.program.forced(group, lanes);
+        self.apply_event(group, lanes);
+    }
+
+    //the given lanes of a group stay at value until they are released with None, like a stuck-at fault
+    //groups the optimization pass merged into another one don't have a state of their own to force
+    pub(crate) fn force_group(&mut self, group: usize, lanes: u64, value: Option<bool>) -> Result<(), String> {
+        if self.program.alias[group] as usize != group {
+            return Err(format!("{} was merged into another group by the optimization pass", describe_group(self, group)));
+        }
+        match value {
+            Some(true) => {
+                self.program.force_mask[group] |= lanes;
+                self.program.force_value[group] |= lanes;
+            }
+            Some(false) => {
+                self.program.force_mask[group] |= lanes;
+                self.program.force_value[group] &= !lanes;
             }
+            None => self.program.force_mask[group] &= !lanes
+        }
+        let value = self.program.forced(group, self.program.state[group]);
+        self.program.projected[group] = value;
+        self.apply_event(group, value);
+        //a released gate computes its value again, latches keep the one they were forced to
+        if matches!(Opcode::from_component_type(self.logic_components[group].component_type), Some(opcode) if opcode.is_combinational()) {
+            self.program.dirty[group] = !0;
+        }
+        Ok(())
+    }
+
+    //what lane 0 of a group is forced to, if it is
+    pub(crate) fn forced_value(&self, group: usize) -> Option<bool> {
+        if self.program.force_mask[group] & 1 == 0 {
+            return None;
+        }
+        Some(self.program.force_value[group] & 1 != 0)
+    }
+
+    //for groups without inputs, nothing in the simulation marks them dirty again so they keep the value
+    pub(crate) fn drive_group(&mut self, group: usize, lanes: u64){
+        self.set_group_lanes(group, lanes);
+        self.program.dirty[group] = 0;
+    }
+
+    fn clear_changed_groups(&mut self){
+        for group in std::mem::take(&mut self.changed_groups){
+            self.program.changed[group as usize] = false;
+        }
+    }
+
+    //true once a tick passes without any group changing, false if that doesn't happen within max_ticks
+    pub(crate) fn run_until_stable(&mut self, max_ticks: u64) -> bool {
+        for _ in 0..max_ticks {
+            self.clear_changed_groups();
+            self.update_canvas();
+            if self.changed_groups.is_empty() && self.program.events.is_empty() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+//lanes for input bit n when every lane gets a different combination, lane i sees bit n of i
+//bits from 6 up are the same in all 64 lanes and have to be stepped by the caller
+pub fn lane_pattern(bit: usize) -> u64 {
+    const PATTERNS: [u64; 6] = [0xAAAAAAAAAAAAAAAA, 0xCCCCCCCCCCCCCCCC, 0xF0F0F0F0F0F0F0F0, 0xFF00FF00FF00FF00, 0xFFFF0000FFFF0000, 0xFFFFFFFF00000000];
+    if bit < PATTERNS.len() { PATTERNS[bit] } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_canvas::{canvas, compiled, group_at};
+
+    //two input latches both drive the latch in the middle, which drives a light
+    const TOGGLE: [&str; 3] = [
+        "lw--r",
+        "....lw-rL",
+        "lw--r",
+    ];
+
+    //eight readers, so six address bits, on top of a framebuffer four times as wide as it is high
+    const WIDE_FRAMEBUFFER: [&str; 5] = [
+        "r.r.r.r.r.r.r.r.",
+        "ffffffffffffffff",
+        "ffffffffffffffff",
+        "ffffffffffffffff",
+        "ffffffffffffffff",
+    ];
+
+    #[test]
+    fn framebuffer_address_bits_follow_its_shape(){
+        let wide = compiled(&WIDE_FRAMEBUFFER);
+        let group = group_at(&wide, 0, 1);
+        assert_eq!(wide.framebuffer_layout(group), (4, 2));
+        assert_eq!(wide.logic_components[group].memory_size, (16, 4));
+        let rows: Vec<String> = (0..16).map(|y| format!("{}ffff", if y % 2 == 0 && y < 15 { "r" } else { "." })).collect();
+        let tall = compiled(&rows.iter().map(|row| row.as_str()).collect::<Vec<&str>>());
+        let group = group_at(&tall, 1, 0);
+        assert_eq!(tall.framebuffer_layout(group), (2, 4));
+        let mut square = canvas(&["r.r.r.r.", "ffffffff"]);
+        crate::test_canvas::draw(&mut square, 0, 2, &["ffffffff"; 7]);
+        square.compile_scene();
+        assert_eq!(square.framebuffer_layout(group_at(&square, 0, 1)), (1, 1));
+    }
+
+    //18 readers are 16 address bits, data and strobe. 7 bits per axis are used and the two address bits after them are left over
+    #[test]
+    fn framebuffer_address_bits_past_its_layout_are_reported(){
+        let component_data = compiled(&["r.".repeat(18).as_str(), "f".repeat(36).as_str(), "f".repeat(36).as_str()]);
+        let group = group_at(&component_data, 0, 1);
+        assert_eq!(component_data.framebuffer_layout(group), (7, 7));
+        assert_eq!(component_data.logic_components[group].memory_size, (128, 128));
+        let inputs = &component_data.logic_components[group].component_before;
+        assert_eq!(component_data.ignored_inputs(group), &inputs[14..16]);
+        assert_eq!(component_data.ignored_input_warnings().len(), 1);
+        assert!(compiled(&WIDE_FRAMEBUFFER).ignored_input_warnings().is_empty());
+    }
+
+    //readers on top of a display are its inputs, left to right
+    fn display(readers: usize, cell: char) -> ComponentData {
+        compiled(&["r.".repeat(readers).as_str(), cell.to_string().repeat(readers * 2).as_str()])
+    }
+
+    #[test]
+    fn inputs_a_display_doesnt_use_are_reported(){
+        for (readers, cell, ignored) in [(7, 's', 0), (9, 's', 2), (4, 'h', 0), (5, 'h', 1)] {
+            let component_data = display(readers, cell);
+            let group = group_at(&component_data, 0, 1);
+            let inputs = &component_data.logic_components[group].component_before;
+            assert_eq!(component_data.ignored_inputs(group), &inputs[readers - ignored..]);
+            //the readers furthest to the right are the ones left over
+            assert!(inputs.iter().enumerate().all(|(index, input)| component_data.logic_components[*input as usize].elements[0].0 == index * 2));
+            assert_eq!(component_data.ignored_input_warnings().len(), (ignored > 0) as usize);
         }
     }
-}
\ No newline at end of file
+
+    //a clock blinking a light, a latch with a light and a framebuffer the clock writes to
+    const VIEWED: [&str; 8] = [
+        "cw-r!w-rL",
+        "",
+        "lw-rL",
+        "",
+        "cw-------",
+        "..r.r.r.r",
+        "..fffffff",
+        "",
+    ];
+
+    //the render loop draws from a copy that only gets the simulated state on frames without edits
+    #[test]
+    fn views_draw_what_the_canvas_simulates(){
+        let mut component_data = compiled(&VIEWED);
+        let (light, latch, framebuffer) = (group_at(&component_data, 8, 0), group_at(&component_data, 0, 2), group_at(&component_data, 2, 6));
+        component_data.toggle_probe(light);
+        let mut view = ComponentData::default();
+        component_data.sync_view(&mut view, true);
+        for tick in 0..40 {
+            component_data.update_canvas();
+            let edited = tick % 10 == 5;
+            if edited {
+                component_data.toggle_latch(0, 2);
+                component_data.force_group(group_at(&component_data, 4, 2), !0, if tick < 20 { Some(true) } else { None }).unwrap();
+            }
+            if tick == 25 {
+                component_data.toggle_probe(latch);
+                component_data.set_heatmap(true);
+            }
+            component_data.sync_view(&mut view, edited || tick == 25);
+            view.update_cell_image(true);
+            component_data.cell_image_sim_view = None;
+            component_data.update_cell_image(true);
+            assert!(view.cell_image.pixels == component_data.cell_image.pixels, "cells differ after tick {}", tick);
+            assert_eq!(view.ticks, component_data.ticks);
+            assert_eq!(view.logic_components[framebuffer].memory, component_data.logic_components[framebuffer].memory);
+            assert_eq!(view.analyzer.probes.len(), component_data.analyzer.probes.len());
+            for (view_probe, probe) in view.analyzer.probes.iter().zip(component_data.analyzer.probes.iter()){
+                assert!(view_probe.transitions == probe.transitions, "probe of group {} differs after tick {}", probe.group, tick);
+            }
+            for group in 0..component_data.logic_components.len() {
+                assert_eq!(view.forced_value(group), component_data.forced_value(group));
+            }
+        }
+        assert!(component_data.logic_components[framebuffer].memory.iter().any(|pixel| *pixel != 0));
+    }
+
+    //the ticks the first and the second input latch are toggled at, one history per lane
+    const HISTORIES: [(&[u64], &[u64]); 4] = [(&[0, 5], &[]), (&[], &[2]), (&[1, 7], &[3]), (&[], &[])];
+
+    fn toggles(history: &[u64], tick: u64) -> bool {
+        history.contains(&tick)
+    }
+
+    #[test]
+    fn latches_only_toggle_in_lanes_whose_inputs_changed(){
+        for delay_mode in [DelayMode::Unit, DelayMode::Zero, DelayMode::Timed] {
+            let mut lanes = compiled(&TOGGLE);
+            lanes.set_delay_mode(delay_mode);
+            let mut scalar: Vec<ComponentData> = HISTORIES.iter().map(|_| compiled(&TOGGLE)).collect();
+            for component_data in scalar.iter_mut(){
+                component_data.set_delay_mode(delay_mode);
+            }
+            let (a, b, latch, light) = (group_at(&lanes, 0, 0), group_at(&lanes, 0, 2), group_at(&lanes, 4, 1), group_at(&lanes, 8, 1));
+            for tick in 0..12 {
+                for (input, select) in [(a, 0), (b, 1)] {
+                    let mut mask = 0;
+                    for (lane, history) in HISTORIES.iter().enumerate(){
+                        if toggles(if select == 0 { history.0 } else { history.1 }, tick) {
+                            mask |= 1 << lane;
+                            let state = scalar[lane].program.state[input];
+                            scalar[lane].set_group_lanes(input, !state);
+                        }
+                    }
+                    let state = lanes.program.state[input];
+                    lanes.set_group_lanes(input, state ^ mask);
+                }
+                lanes.update_canvas();
+                for (lane, component_data) in scalar.iter_mut().enumerate(){
+                    component_data.update_canvas();
+                    for group in [latch, light] {
+                        assert_eq!(lanes.program.value(group) >> lane & 1, component_data.program.value(group) & 1,
+                            "{} in lane {} after tick {} in {}", describe_group(&lanes, group), lane, tick, delay_mode.name());
+                    }
+                }
+            }
+        }
+    }
+}
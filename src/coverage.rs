@@ -0,0 +1,67 @@
+use crate::content::{ComponentData, ComponentType};
+use crate::region::describe_group;
+
+pub const NEVER_TOGGLED_COLOR: (u8, u8, u8) = (60, 60, 90);
+//from a few toggles to HEAT_SCALE toggles and more, on a log scale
+const HEAT_COLORS: [(u8, u8, u8); 4] = [(0, 90, 255), (0, 220, 120), (255, 220, 0), (255, 30, 0)];
+const HEAT_SCALE: u32 = 1 << 16;
+
+pub fn heat_color(toggles: u32) -> (u8, u8, u8) {
+    if toggles == 0 {
+        return NEVER_TOGGLED_COLOR;
+    }
+    let heat = (toggles.min(HEAT_SCALE) as f32).log2() / (HEAT_SCALE as f32).log2() * (HEAT_COLORS.len() - 1) as f32;
+    let index = (heat as usize).min(HEAT_COLORS.len() - 2);
+    let fraction = heat - index as f32;
+    let (from, to) = (HEAT_COLORS[index], HEAT_COLORS[index + 1]);
+    let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * fraction).round() as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+//comments and crosses never change, so they don't count
+pub struct Coverage {
+    pub groups: usize,
+    pub never_toggled: Vec<usize>,
+}
+
+pub fn collect(component_data: &ComponentData) -> Coverage {
+    let mut coverage = Coverage{groups: 0, never_toggled: vec![]};
+    for (group, logic_component) in component_data.logic_components.iter().enumerate(){
+        if logic_component.component_type == ComponentType::COMMENT || logic_component.component_type == ComponentType::CROSS {
+            continue;
+        }
+        coverage.groups += 1;
+        if component_data.program.toggles[group] == 0 {
+            coverage.never_toggled.push(group);
+        }
+    }
+    coverage
+}
+
+impl Coverage {
+    pub fn toggled(&self) -> usize {
+        self.groups - self.never_toggled.len()
+    }
+
+    pub fn percentage(&self) -> f64 {
+        if self.groups == 0 {
+            return 100.0;
+        }
+        self.toggled() as f64 * 100.0 / self.groups as f64
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} of {} groups toggled ({:.1}%)", self.toggled(), self.groups, self.percentage())
+    }
+
+    pub fn report(&self, component_data: &ComponentData) -> String {
+        let mut text = format!("{}\n", self.summary());
+        if !self.never_toggled.is_empty() {
+            text.push_str("never toggled:\n");
+            for group in self.never_toggled.iter(){
+                text.push_str(&format!("  {}\n", describe_group(component_data, *group)));
+            }
+        }
+        text
+    }
+}
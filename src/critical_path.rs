@@ -0,0 +1,103 @@
+use crate::content::{ComponentData, ComponentType};
+use crate::program::{find_strongly_connected_components, Opcode};
+use crate::region::describe_group;
+
+//groups is the path from the stateful group where it starts to the one where it ends
+//endpoints are the stateful groups reached by combinational logic with the longest path into each
+pub struct CriticalPath {
+    pub groups: Vec<usize>,
+    pub ticks: u32,
+    pub endpoints: Vec<(usize, u32)>,
+    pub loops: usize,
+}
+
+fn opcode(component_data: &ComponentData, group: usize) -> Option<Opcode> {
+    Opcode::from_component_type(component_data.logic_components[group].component_type)
+}
+
+fn is_stateful(component_data: &ComponentData, group: usize) -> bool {
+    matches!(opcode(component_data, group), Some(opcode) if !opcode.is_combinational())
+}
+
+//in unit delay mode a signal needs one tick for every reader it passes, so the length of a path is its reader count
+//loops of gates never settle and are left out
+pub fn find(component_data: &ComponentData) -> Option<CriticalPath> {
+    let logic_components = &component_data.logic_components;
+    let combinational = |group: usize| matches!(opcode(component_data, group), Some(opcode) if opcode.is_combinational());
+    let mut in_loop = vec![false; logic_components.len()];
+    let mut order = vec![];
+    let mut loops = 0;
+    //strongly connected components come out of tarjan in reverse topological order
+    for component in find_strongly_connected_components(logic_components, &combinational).into_iter().rev(){
+        if component.len() > 1 {
+            loops += 1;
+            for group in component {
+                in_loop[group] = true;
+            }
+        } else {
+            order.push(component[0]);
+        }
+    }
+    let mut ticks: Vec<Option<u32>> = vec![None; logic_components.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; logic_components.len()];
+    let longest_input = |group: usize, ticks: &Vec<Option<u32>>| -> Option<(u32, usize)> {
+        let mut longest: Option<(u32, usize)> = None;
+        for input in logic_components[group].component_before.iter(){
+            let input = *input as usize;
+            let input_ticks = if is_stateful(component_data, input) { Some(0) } else if in_loop[input] { None } else { ticks[input] };
+            if let Some(input_ticks) = input_ticks {
+                if longest.is_none_or(|longest| input_ticks > longest.0) {
+                    longest = Some((input_ticks, input));
+                }
+            }
+        }
+        longest
+    };
+    for group in order {
+        if let Some((input_ticks, input)) = longest_input(group, &ticks) {
+            let reader = (logic_components[group].component_type == ComponentType::READ_FROM_WIRE) as u32;
+            ticks[group] = Some(input_ticks + reader);
+            previous[group] = Some(input);
+        }
+    }
+    let mut endpoints = vec![];
+    let mut longest: Option<(u32, usize, usize)> = None;
+    for group in (0..logic_components.len()).filter(|group| is_stateful(component_data, *group)){
+        if let Some((input_ticks, input)) = longest_input(group, &ticks) {
+            endpoints.push((group, input_ticks));
+            if longest.is_none_or(|longest| input_ticks > longest.0) {
+                longest = Some((input_ticks, group, input));
+            }
+        }
+    }
+    let (path_ticks, end, mut group) = longest?;
+    let mut groups = vec![end, group];
+    while !is_stateful(component_data, group) {
+        group = previous[group].unwrap();
+        groups.push(group);
+    }
+    groups.reverse();
+    endpoints.sort_by_key(|endpoint| std::cmp::Reverse(endpoint.1));
+    Some(CriticalPath{groups, ticks: path_ticks, endpoints, loops})
+}
+
+impl CriticalPath {
+    pub fn report(&self, component_data: &ComponentData) -> String {
+        let mut text = format!("critical path: {} ticks from {} to {} through {} groups\n", self.ticks,
+            describe_group(component_data, self.groups[0]), describe_group(component_data, *self.groups.last().unwrap()), self.groups.len());
+        text.push_str(&format!("in unit delay mode a clock driving these latches needs at least {} ticks between edges\n", self.ticks));
+        let mut depths: Vec<(u32, usize)> = vec![];
+        for (_, ticks) in self.endpoints.iter(){
+            match depths.last_mut() {
+                Some(depth) if depth.0 == *ticks => depth.1 += 1,
+                _ => depths.push((*ticks, 1))
+            }
+        }
+        let depths: Vec<String> = depths.iter().map(|(ticks, count)| format!("{} ticks: {}", ticks, count)).collect();
+        text.push_str(&format!("logic depth in front of {} latches, clocks and framebuffers: {}\n", self.endpoints.len(), depths.join(", ")));
+        if self.loops > 0 {
+            text.push_str(&format!("{} loops of gates were left out\n", self.loops));
+        }
+        text
+    }
+}
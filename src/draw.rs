@@ -0,0 +1,385 @@
+use crate::content::{HEIGHT, WIDTH, ComponentType, COLORS, NAMES, ComponentData, MiscData};
+use crate::coverage;
+use crate::font;
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::region::describe_group;
+use crate::renderer::Renderer;
+use crate::analyzer::MAX_PROBES;
+use crate::MSPT_OPTIONS;
+
+pub const LABEL_COLOR: (u8, u8, u8) = (200, 204, 210);
+pub const TOOLBAR_HEIGHT: u32 = 32;
+pub const STATUS_BAR_HEIGHT: u32 = 20;
+pub const PANEL_HEIGHT: u32 = TOOLBAR_HEIGHT + STATUS_BAR_HEIGHT;
+//the logic analyzer is docked between the canvas and the toolbar, with a row for every probe it can show
+pub const ANALYZER_HEIGHT: u32 = ANALYZER_HEADER_HEIGHT + MAX_PROBES as u32 * ANALYZER_ROW_HEIGHT;
+pub const ANALYZER_TOP: u32 = HEIGHT * 2;
+pub const TOOLBAR_TOP: u32 = ANALYZER_TOP + ANALYZER_HEIGHT;
+const UPSTREAM_COLOR: (u8, u8, u8) = (0, 170, 255);
+const DOWNSTREAM_COLOR: (u8, u8, u8) = (255, 150, 0);
+const CRITICAL_PATH_COLOR: (u8, u8, u8) = (255, 40, 200);
+const FAULT_COLOR: (u8, u8, u8) = (255, 0, 0);
+const ANALYZER_HEADER_HEIGHT: u32 = 12;
+const ANALYZER_ROW_HEIGHT: u32 = 12;
+const ANALYZER_NAME_WIDTH: u32 = 240;
+const ANALYZER_GRID_COLOR: (u8, u8, u8) = (60, 64, 76);
+const ANALYZER_CURSOR_COLOR: (u8, u8, u8) = (255, 220, 0);
+const BREAKPOINT_COLOR: (u8, u8, u8) = (150, 110, 255);
+const BREAKPOINT_TRIGGER_COLOR: (u8, u8, u8) = (255, 255, 255);
+
+pub fn get_color(component_type: ComponentType, enabled: bool) -> (u8, u8, u8){
+    if enabled {
+        COLORS[component_type as usize].1
+    } else {
+        COLORS[component_type as usize].0
+    }
+}
+
+pub fn draw_canvas(component_data: &mut ComponentData, canvas: &mut dyn Renderer, sim_view: bool){
+    let color = COLORS[0].0;
+    canvas.set_draw_color((color.0 / 2, color.1 / 2, color.2 / 2));
+    canvas.clear();
+    component_data.update_cell_image(sim_view);
+    //rounded once so every cell lands where the overlays put it, exports have whole pixel positions and aren't rounded at all
+    let (x, y) = ((component_data.position_on_screen.0 * 2.0).round() as i32, (component_data.position_on_screen.1 * 2.0).round() as i32);
+    canvas.draw_cell_image(&mut component_data.cell_image, x, y, (WIDTH as f32 * component_data.zoom * 2.0).round() as u32, (HEIGHT as f32 * component_data.zoom * 2.0).round() as u32);
+    if sim_view && !component_data.heatmap {
+        draw_canvas_components(component_data, canvas);
+    }
+    draw_labels(component_data, canvas);
+}
+
+pub fn draw_text(text: &str, x: f32, y: f32, pixel_size: f32, color: (u8, u8, u8), canvas: &mut dyn Renderer){
+    canvas.set_draw_color(color);
+    for (index, character) in text.chars().enumerate(){
+        let character_x = x + (index as u32 * (GLYPH_WIDTH + 1)) as f32 * pixel_size;
+        if character_x > canvas.size().0 as f32 {
+            break;
+        }
+        for i in 0..GLYPH_WIDTH {
+            for j in 0..GLYPH_HEIGHT {
+                if font::is_pixel_set(character, i, j) {
+                    canvas.fill_rect((character_x + i as f32 * pixel_size).round() as i32, (y + j as f32 * pixel_size).round() as i32, pixel_size.round().max(1.0) as u32, pixel_size.round().max(1.0) as u32);
+                }
+            }
+        }
+    }
+}
+
+fn draw_labels(component_data: &ComponentData, canvas: &mut dyn Renderer){
+    for label in component_data.labels.iter(){
+        draw_text(&label.text, (label.position.0 as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0, (label.position.1 as f32 * component_data.zoom + component_data.position_on_screen.1) * 2.0, component_data.zoom * 2.0, LABEL_COLOR, canvas);
+    }
+}
+
+//displays and framebuffers are drawn over the cell image
+fn draw_canvas_components(component_data: &ComponentData, canvas: &mut dyn Renderer){
+    for i in 0..component_data.logic_components.len(){
+        if component_data.logic_components[i].component_type.is_display() {
+            draw_display(component_data, i, canvas);
+        }
+        if component_data.logic_components[i].component_type == ComponentType::FRAMEBUFFER {
+            draw_framebuffer(component_data, i, canvas);
+        }
+    }
+}
+
+fn draw_framebuffer(component_data: &ComponentData, gate_index: usize, canvas: &mut dyn Renderer){
+    let logic_component = &component_data.logic_components[gate_index];
+    if logic_component.memory.is_empty() {
+        return;
+    }
+    let (min, max) = logic_component.bounding_box();
+    let pixel_w = (max.0 - min.0 + 1) as f32 * component_data.zoom * 2.0 / logic_component.memory_size.0 as f32;
+    let pixel_h = (max.1 - min.1 + 1) as f32 * component_data.zoom * 2.0 / logic_component.memory_size.1 as f32;
+    let origin = ((min.0 as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0, (min.1 as f32 * component_data.zoom + component_data.position_on_screen.1) * 2.0);
+    for row in 0..logic_component.memory_size.1 {
+        for column in 0..logic_component.memory_size.0 {
+            let color = get_color(ComponentType::FRAMEBUFFER, logic_component.memory[row * logic_component.memory_size.0 + column] & 1 != 0);
+            let x = (origin.0 + column as f32 * pixel_w).round();
+            let y = (origin.1 + row as f32 * pixel_h).round();
+            canvas.set_draw_color(color);
+            canvas.fill_rect(x as i32, y as i32, ((origin.0 + (column + 1) as f32 * pixel_w).round() - x).max(1.0) as u32, ((origin.1 + (row + 1) as f32 * pixel_h).round() - y).max(1.0) as u32);
+        }
+    }
+}
+
+fn draw_display(component_data: &ComponentData, gate_index: usize, canvas: &mut dyn Renderer){
+    let (min, max) = component_data.logic_components[gate_index].bounding_box();
+    //glyph is drawn on a 4x7 grid of cells scaled to the bounding box of the display
+    let cell_w = (max.0 - min.0 + 1) as f32 * component_data.zoom * 2.0 / 4.0;
+    let cell_h = (max.1 - min.1 + 1) as f32 * component_data.zoom * 2.0 / 7.0;
+    let origin = ((min.0 as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0, (min.1 as f32 * component_data.zoom + component_data.position_on_screen.1) * 2.0);
+    //x, y, width, height of segments a to g in grid cells
+    let segment_rects: [(f32, f32, f32, f32); 7] = [(1.0, 0.0, 2.0, 1.0), (3.0, 1.0, 1.0, 2.0), (3.0, 4.0, 1.0, 2.0), (1.0, 6.0, 2.0, 1.0), (0.0, 4.0, 1.0, 2.0), (0.0, 1.0, 1.0, 2.0), (1.0, 3.0, 2.0, 1.0)];
+    let segments = component_data.display_segments(gate_index);
+    let component_type = component_data.logic_components[gate_index].component_type;
+    canvas.set_draw_color(COLORS[0].0);
+    canvas.fill_rect(origin.0.round() as i32, origin.1.round() as i32, (cell_w * 4.0).round().max(1.0) as u32, (cell_h * 7.0).round().max(1.0) as u32);
+    for (segment, rect) in segment_rects.iter().enumerate(){
+        let color = get_color(component_type, segments & (1 << segment) != 0);
+        canvas.set_draw_color(color);
+        canvas.fill_rect((origin.0 + rect.0 * cell_w).round() as i32, (origin.1 + rect.1 * cell_h).round() as i32, (rect.2 * cell_w).round().max(1.0) as u32, (rect.3 * cell_h).round().max(1.0) as u32);
+    }
+}
+
+fn draw_group_outline(component_data: &ComponentData, group: usize, color: (u8, u8, u8), canvas: &mut dyn Renderer){
+    canvas.set_draw_color(color);
+    for element in component_data.logic_components[group].elements.iter(){
+        canvas.draw_rect(((element.0 as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0).round() as i32, ((element.1 as f32 * component_data.zoom + component_data.position_on_screen.1) * 2.0).round() as i32, (component_data.zoom * 2.0).round().max(1.0) as u32, (component_data.zoom * 2.0).round().max(1.0) as u32);
+    }
+}
+
+pub fn draw_critical_path(component_data: &ComponentData, groups: &[usize], canvas: &mut dyn Renderer){
+    for group in groups.iter(){
+        draw_group_outline(component_data, *group, CRITICAL_PATH_COLOR, canvas);
+    }
+}
+
+pub fn is_over_analyzer(y: i32) -> bool {
+    y >= ANALYZER_TOP as i32 && y < TOOLBAR_TOP as i32
+}
+
+//the current tick is at the right edge, zoom is in pixels per tick
+pub fn get_analyzer_tick(component_data: &ComponentData, zoom: f32, x: i32) -> Option<u64> {
+    if x < ANALYZER_NAME_WIDTH as i32 {
+        return None;
+    }
+    let ticks_back = ((WIDTH * 2) as i32 - x) as f32 / zoom;
+    Some(component_data.ticks.saturating_sub(ticks_back.round() as u64))
+}
+
+//markers every 1, 2 or 5 times a power of ten ticks, at least 80 pixels apart
+fn get_marker_step(zoom: f32) -> u64 {
+    let mut step = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            if (step * factor) as f32 * zoom >= 80.0 {
+                return step * factor;
+            }
+        }
+        step *= 10;
+    }
+}
+
+pub fn draw_analyzer(component_data: &ComponentData, zoom: f32, cursor: Option<u64>, canvas: &mut dyn Renderer){
+    let top = ANALYZER_TOP as i32;
+    let right = (WIDTH * 2) as i32;
+    let height = ANALYZER_HEIGHT as i32;
+    let x_of = |tick: u64| right - ((component_data.ticks - tick.min(component_data.ticks)) as f32 * zoom).round() as i32;
+    let first_tick = component_data.ticks.saturating_sub(((right - ANALYZER_NAME_WIDTH as i32) as f32 / zoom) as u64);
+    let color = COLORS[0].0;
+    canvas.set_draw_color((color.0 / 2, color.1 / 2, color.2 / 2));
+    canvas.fill_rect(0, top, WIDTH * 2, height as u32);
+
+    let step = get_marker_step(zoom);
+    let mut marker = first_tick.div_ceil(step) * step;
+    while marker <= component_data.ticks {
+        canvas.set_draw_color(ANALYZER_GRID_COLOR);
+        canvas.fill_rect(x_of(marker), top, 1, height as u32);
+        draw_text(&marker.to_string(), (x_of(marker) + 3) as f32, (top + 3) as f32, 1.0, LABEL_COLOR, canvas);
+        marker += step;
+    }
+    let header = match cursor {
+        Some(cursor) => format!("CURSOR: {}", cursor),
+        None if component_data.analyzer.probes.is_empty() => "LOGIC ANALYZER: RIGHT CLICK GROUPS WHILE SIMULATING TO PROBE THEM".to_string(),
+        None => "LOGIC ANALYZER".to_string()
+    };
+    draw_text(&header, 4.0, (top + 3) as f32, 1.0, LABEL_COLOR, canvas);
+
+    for (index, probe) in component_data.analyzer.probes.iter().enumerate(){
+        let y = top + (ANALYZER_HEADER_HEIGHT + index as u32 * ANALYZER_ROW_HEIGHT) as i32;
+        let (high, low) = (y + 2, y + ANALYZER_ROW_HEIGHT as i32 - 3);
+        let value = cursor.and_then(|cursor| probe.value_at(cursor)).map_or(String::new(), |value| format!(" = {}", value as u8));
+        let name: String = describe_group(component_data, probe.group).chars().take(((ANALYZER_NAME_WIDTH - 8) / (GLYPH_WIDTH + 1)) as usize - value.len()).collect();
+        draw_text(&(name + &value), 4.0, (y + 3) as f32, 1.0, LABEL_COLOR, canvas);
+        canvas.set_draw_color(COLORS[ComponentType::LIGHT as usize].1);
+        //a transition per pixel column at most, busy signals turn into a solid block
+        let start = probe.transitions.partition_point(|transition| transition.0 <= first_tick).saturating_sub(1);
+        let mut last_edge = i32::MIN;
+        for i in start..probe.transitions.len(){
+            let (tick, value) = probe.transitions[i];
+            let from = x_of(tick.max(first_tick));
+            let to = probe.transitions.get(i + 1).map_or(right, |next| x_of(next.0));
+            if i > start && from != last_edge {
+                canvas.fill_rect(from, high, 1, (low - high) as u32 + 1);
+                last_edge = from;
+            }
+            if to > from {
+                canvas.fill_rect(from, if value { high } else { low }, (to - from) as u32, 1);
+            }
+        }
+    }
+    if let Some(cursor) = cursor.filter(|cursor| *cursor >= first_tick) {
+        canvas.set_draw_color(ANALYZER_CURSOR_COLOR);
+        canvas.fill_rect(x_of(cursor), top, 1, height as u32);
+    }
+}
+
+//groups with a breakpoint, the ones of the breakpoint that paused the simulation stand out
+pub fn draw_breakpoints(component_data: &ComponentData, trigger: &[usize], canvas: &mut dyn Renderer){
+    for breakpoint in component_data.breakpoints.iter(){
+        for group in breakpoint.condition.groups(){
+            draw_group_outline(component_data, group, BREAKPOINT_COLOR, canvas);
+        }
+    }
+    for group in trigger.iter(){
+        draw_group_outline(component_data, *group, BREAKPOINT_TRIGGER_COLOR, canvas);
+    }
+}
+
+//groups stuck at a value in lane 0
+pub fn draw_faults(component_data: &ComponentData, canvas: &mut dyn Renderer){
+    for group in 0..component_data.logic_components.len(){
+        if component_data.forced_value(group).is_some() {
+            draw_group_outline(component_data, group, FAULT_COLOR, canvas);
+        }
+    }
+}
+
+//the heatmap legend in the top left corner with the first groups that never toggled
+pub fn draw_coverage(component_data: &ComponentData, canvas: &mut dyn Renderer){
+    let coverage = coverage::collect(component_data);
+    let mut lines = vec![format!("COVERAGE: {}", coverage.summary())];
+    if !coverage.never_toggled.is_empty() {
+        lines.push("NEVER TOGGLED:".to_string());
+    }
+    for (index, group) in coverage.never_toggled.iter().enumerate(){
+        if index == 10 {
+            lines.push(format!("+{} MORE", coverage.never_toggled.len() - 10));
+            break;
+        }
+        lines.push(describe_group(component_data, *group));
+    }
+    let width = lines.iter().map(|line| font::text_width(line)).max().unwrap() * 2 + 8;
+    let height = lines.len() as u32 * (GLYPH_HEIGHT + 2) * 2 + 8;
+    let color = COLORS[0].0;
+    canvas.set_draw_color((color.0 / 2, color.1 / 2, color.2 / 2));
+    canvas.fill_rect(0, 0, width, height);
+    for (index, line) in lines.iter().enumerate(){
+        let color = if index < 2 { LABEL_COLOR } else { coverage::NEVER_TOGGLED_COLOR };
+        draw_text(line, 4.0, 4.0 + (index as u32 * (GLYPH_HEIGHT + 2) * 2) as f32, 2.0, color, canvas);
+    }
+}
+
+fn format_group_list(groups: &[u32]) -> String {
+    let mut text = String::new();
+    for (index, group) in groups.iter().enumerate(){
+        if index == 10 {
+            text.push_str(&format!(" +{}", groups.len() - 10));
+            break;
+        }
+        if index != 0 {
+            text.push_str(", ");
+        }
+        text.push_str(&group.to_string());
+    }
+    if text.is_empty() {
+        text.push_str("NONE");
+    }
+    text
+}
+
+pub fn draw_inspector(component_data: &ComponentData, group: usize, mouse: (i32, i32), canvas: &mut dyn Renderer){
+    let logic_component = &component_data.logic_components[group];
+    for before in logic_component.component_before.iter(){
+        draw_group_outline(component_data, *before as usize, UPSTREAM_COLOR, canvas);
+    }
+    for after in logic_component.component_after.iter(){
+        draw_group_outline(component_data, *after as usize, DOWNSTREAM_COLOR, canvas);
+    }
+    draw_group_outline(component_data, group, LABEL_COLOR, canvas);
+
+    let mut lines = vec![
+        format!("GROUP {}: {}", group, NAMES[logic_component.component_type as usize]),
+        format!("ELEMENTS: {}", logic_component.elements.len()),
+        format!("ENABLED: {}", if component_data.is_group_enabled(group) { "ON" } else { "OFF" }),
+        format!("DELAY: {}", logic_component.delay),
+        format!("FORCED: {}", match component_data.forced_value(group) { Some(value) => format!("STUCK AT {}", value as u8), None => "NO".to_string() }),
+        format!("BEFORE: {}", format_group_list(&logic_component.component_before)),
+        format!("AFTER: {}", format_group_list(&logic_component.component_after)),
+    ];
+    let mut colors = vec![LABEL_COLOR, LABEL_COLOR, LABEL_COLOR, LABEL_COLOR, LABEL_COLOR, UPSTREAM_COLOR, DOWNSTREAM_COLOR];
+    let ignored = component_data.ignored_inputs(group);
+    if !ignored.is_empty() {
+        lines.push(format!("IGNORED: {}", format_group_list(ignored)));
+        colors.push(FAULT_COLOR);
+    }
+    let width = lines.iter().map(|line| font::text_width(line)).max().unwrap() * 2 + 8;
+    let height = lines.len() as u32 * (GLYPH_HEIGHT + 2) * 2 + 8;
+    let x = std::cmp::max(std::cmp::min(mouse.0 + 16, (WIDTH * 2) as i32 - width as i32), 0);
+    let y = std::cmp::max(std::cmp::min(mouse.1 + 16, (HEIGHT * 2) as i32 - height as i32), 0);
+    let color = COLORS[0].0;
+    canvas.set_draw_color((color.0 / 2, color.1 / 2, color.2 / 2));
+    canvas.fill_rect(x, y, width, height);
+    for (index, line) in lines.iter().enumerate(){
+        draw_text(line, (x + 4) as f32, (y + 4) as f32 + (index as u32 * (GLYPH_HEIGHT + 2) * 2) as f32, 2.0, colors[index], canvas);
+    }
+}
+
+pub fn draw_toolbar(misc_data: &MiscData, canvas: &mut dyn Renderer){
+    let color = COLORS[0].0;
+    canvas.set_draw_color((color.0 / 2, color.1 / 2, color.2 / 2));
+    canvas.fill_rect(0, TOOLBAR_TOP as i32, WIDTH * 2, PANEL_HEIGHT);
+    for (index, (x, entry_width)) in get_toolbar_entries().into_iter().enumerate(){
+        let i = index as u32 + 1;
+        let y = TOOLBAR_TOP as i32;
+        let swatch = COLORS[i as usize].0;
+        canvas.set_draw_color(swatch);
+        canvas.fill_rect(x + 3, y + 4, entry_width - 6, 12);
+        let swatch = COLORS[i as usize].1;
+        canvas.set_draw_color(swatch);
+        canvas.fill_rect(x + 3, y + 4, (entry_width - 6) / 4, 12);
+        draw_text(NAMES[i as usize], (x + 3) as f32, (y + 20) as f32, 1.0, LABEL_COLOR, canvas);
+        if misc_data.selected_type as u32 == i {
+            canvas.set_draw_color(LABEL_COLOR);
+            canvas.draw_rect(x + 1, y + 2, entry_width - 2, TOOLBAR_HEIGHT - 3);
+        }
+    }
+}
+
+pub fn draw_status_bar(component_data: &ComponentData, misc_data: &MiscData, ticks_per_second: u64, cursor: (i32, i32), canvas: &mut dyn Renderer){
+    let mode = if misc_data.run_sim { "SIMULATION" } else { "EDIT" };
+    let mspt = MSPT_OPTIONS[misc_data.selected_mspt as usize];
+    let speed = if mspt == MSPT_OPTIONS[0] {
+        String::from("PAUSED")
+    } else if mspt == 0 {
+        format!("{} TPS (MAX)", ticks_per_second)
+    } else {
+        format!("{} TPS (TARGET {})", ticks_per_second, 1000 / mspt)
+    };
+    let delay_mode = if component_data.optimize { format!("{} OPTIMIZED", component_data.delay_mode.name()) } else { component_data.delay_mode.name().to_string() };
+    let text = match misc_data.breakpoint_value.as_ref() {
+        Some(value) => format!("BREAK WHEN THE SELECTED LATCHES EQUAL: {}_   (ENTER TO ADD, ESCAPE TO CANCEL)", value),
+        None => format!("{}   SPEED: {}   {}   TICK: {}   X: {} Y: {}   {}", mode, speed, delay_mode, component_data.ticks, cursor.0, cursor.1, NAMES[misc_data.selected_type as usize])
+    };
+    draw_text(&text, 4.0, (TOOLBAR_TOP + TOOLBAR_HEIGHT + 3) as f32, 2.0, LABEL_COLOR, canvas);
+}
+
+pub fn draw_to_paste(component_data: &ComponentData, canvas: &mut dyn Renderer, copied_data: &[Vec<u8>], paste: (bool, (i32, i32))) {
+    for i in 0.. copied_data.len(){
+        for j in 0..copied_data[i].len(){
+            if copied_data[i][j] as u32 != ComponentType::NOTHING as u32 {
+                let mut color = COLORS[copied_data[i][j] as usize].0;
+                color.0 = (color.0 as f32 * 3.0/4.0) as u8;
+                color.1 = (color.1 as f32 * 3.0/4.0) as u8;
+                color.2 = (color.2 as f32 * 3.0/4.0) as u8;
+                canvas.set_draw_color(color);
+                canvas.fill_rect((((i as i32 + paste.1.0) as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0).round() as i32, (((j as i32 + paste.1.1) as f32 * component_data.zoom + component_data.position_on_screen.1) * 2.0).round() as i32, (component_data.zoom * 2.0).round() as u32, (component_data.zoom * 2.0).round() as u32);
+
+            }
+        }
+    }
+}
+
+//x and width of the entry of every component type after nothing, each name fits its entry and the space left is shared evenly
+pub fn get_toolbar_entries() -> Vec<(i32, u32)> {
+    let widths: Vec<u32> = (1..ComponentType::NUM_COMPONENTS as usize).map(|i| font::text_width(NAMES[i]) + 6).collect();
+    let spare = (WIDTH * 2).saturating_sub(widths.iter().sum()) / widths.len() as u32;
+    let mut x = 0;
+    widths.iter().map(|width| {
+        let entry = (x, width + spare);
+        x += (width + spare) as i32;
+        entry
+    }).collect()
+}
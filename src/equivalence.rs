@@ -0,0 +1,190 @@
+use crate::content::{ComponentData, DelayMode, NAMES};
+use crate::program::{find_strongly_connected_components, Opcode};
+use crate::region::{compile_region, find_ports, Port, Ports};
+use crate::sat::{Literal, Solver};
+use crate::truth_table::{generate_rows, MAX_INPUTS, MAX_TICKS};
+
+pub struct Circuit {
+    pub region_data: ComponentData,
+    pub ports: Ports,
+}
+
+impl Circuit {
+    pub fn new(component_data: &ComponentData, region: ((i32, i32), (i32, i32))) -> Self {
+        let mut region_data = compile_region(component_data, region);
+        region_data.set_delay_mode(DelayMode::Zero);
+        let ports = find_ports(component_data, region, &region_data);
+        Self{region_data, ports}
+    }
+}
+
+//a canvas and the corners of a region on it
+pub type Region<'a> = (&'a ComponentData, ((i32, i32), (i32, i32)));
+
+pub enum Method {Exhaustive, Sat}
+
+pub struct Counterexample {
+    pub inputs: Vec<bool>,
+    pub outputs_a: Vec<bool>,
+    pub outputs_b: Vec<bool>,
+}
+
+//the port names of both circuits in the order they were paired
+pub struct Equivalence {
+    pub method: Method,
+    pub inputs: Vec<(String, String)>,
+    pub outputs: Vec<(String, String)>,
+    pub counterexample: Option<Counterexample>,
+}
+
+fn pair_names(a: &[Port], b: &[Port]) -> Vec<(String, String)> {
+    a.iter().zip(b.iter()).map(|(a, b)| (a.name.clone(), b.name.clone())).collect()
+}
+
+//ports are paired up by position, the first input of one circuit with the first input of the other and so on
+pub fn check(a: Region, b: Region, force_sat: bool) -> Result<Equivalence, String> {
+    let mut circuit_a = Circuit::new(a.0, a.1);
+    let mut circuit_b = Circuit::new(b.0, b.1);
+    let (ports_a, ports_b) = (&circuit_a.ports, &circuit_b.ports);
+    if ports_a.inputs.len() != ports_b.inputs.len() || ports_a.outputs.len() != ports_b.outputs.len() {
+        return Err(format!("the port lists don't match: {} inputs and {} outputs against {} inputs and {} outputs",
+            ports_a.inputs.len(), ports_a.outputs.len(), ports_b.inputs.len(), ports_b.outputs.len()));
+    }
+    if ports_a.outputs.is_empty() {
+        return Err("found no outputs to compare".to_string());
+    }
+    let mut equivalence = Equivalence{
+        method: Method::Exhaustive,
+        inputs: pair_names(&ports_a.inputs, &ports_b.inputs),
+        outputs: pair_names(&ports_a.outputs, &ports_b.outputs),
+        counterexample: None,
+    };
+    if !force_sat && !ports_a.inputs.is_empty() && ports_a.inputs.len() <= MAX_INPUTS {
+        equivalence.counterexample = check_exhaustive(&mut circuit_a, &mut circuit_b)?;
+        return Ok(equivalence);
+    }
+    equivalence.method = Method::Sat;
+    if let Some(inputs) = check_sat(&circuit_a, &circuit_b)? {
+        //the solver only knows the settled logic, running the inputs through both circuits confirms the difference
+        let outputs_a = simulate(&mut circuit_a, &inputs)?;
+        let outputs_b = simulate(&mut circuit_b, &inputs)?;
+        equivalence.counterexample = Some(Counterexample{inputs, outputs_a, outputs_b});
+    }
+    Ok(equivalence)
+}
+
+fn check_exhaustive(circuit_a: &mut Circuit, circuit_b: &mut Circuit) -> Result<Option<Counterexample>, String> {
+    let rows_a = generate_rows(&mut circuit_a.region_data, &circuit_a.ports)?;
+    let rows_b = generate_rows(&mut circuit_b.region_data, &circuit_b.ports)?;
+    for (row_a, row_b) in rows_a.iter().zip(rows_b.iter()){
+        if row_a.1 != row_b.1 {
+            return Ok(Some(Counterexample{inputs: row_a.0.clone(), outputs_a: row_a.1.clone(), outputs_b: row_b.1.clone()}));
+        }
+    }
+    Ok(None)
+}
+
+//runs one input combination in lane 0
+fn simulate(circuit: &mut Circuit, inputs: &[bool]) -> Result<Vec<bool>, String> {
+    for (port, input) in circuit.ports.inputs.iter().zip(inputs.iter()){
+        circuit.region_data.drive_group(port.group, if *input { !0 } else { 0 });
+    }
+    if !circuit.region_data.run_until_stable(MAX_TICKS) {
+        return Err(format!("the region doesn't settle within {} ticks", MAX_TICKS));
+    }
+    Ok(circuit.ports.outputs.iter().map(|port| circuit.region_data.program.state[port.group] & 1 != 0).collect())
+}
+
+//a miter of both circuits: the shared inputs are free and at least one pair of outputs has to differ
+//returns the inputs of a satisfying assignment, or none if the circuits are equivalent
+fn check_sat(circuit_a: &Circuit, circuit_b: &Circuit) -> Result<Option<Vec<bool>>, String> {
+    let mut solver = Solver::new();
+    let inputs: Vec<Literal> = circuit_a.ports.inputs.iter().map(|_| solver.new_var()).collect();
+    let outputs_a = encode(&mut solver, circuit_a, &inputs)?;
+    let outputs_b = encode(&mut solver, circuit_b, &inputs)?;
+    let mut differences = vec![];
+    for (output_a, output_b) in outputs_a.into_iter().zip(outputs_b){
+        let difference = solver.new_var();
+        solver.add_xor(difference, &[output_a, output_b]);
+        differences.push(difference);
+    }
+    solver.add_clause(&differences);
+    if !solver.solve() {
+        return Ok(None);
+    }
+    Ok(Some(inputs.iter().map(|input| solver.value(*input)).collect()))
+}
+
+//tseitin encoding of the region as it looks once it settled, one variable per group
+//the input ports get the shared literals, everything else has to be combinational and free of loops
+fn encode(solver: &mut Solver, circuit: &Circuit, inputs: &[Literal]) -> Result<Vec<Literal>, String> {
+    let logic_components = &circuit.region_data.logic_components;
+    for component in find_strongly_connected_components(logic_components, &|_| true) {
+        if component.len() > 1 {
+            let position = logic_components[component[0]].elements[0];
+            return Err(format!("the logic at x{} y{} feeds back into itself, loops can't be checked", position.0, position.1));
+        }
+    }
+    let mut literals: Vec<Option<Literal>> = vec![None; logic_components.len()];
+    for (port, input) in circuit.ports.inputs.iter().zip(inputs.iter()){
+        literals[port.group] = Some(*input);
+    }
+    let literals: Vec<Literal> = literals.into_iter().map(|literal| literal.unwrap_or_else(|| solver.new_var())).collect();
+    let is_input: Vec<bool> = (0..logic_components.len()).map(|group| circuit.ports.inputs.iter().any(|port| port.group == group)).collect();
+    for (group, logic_component) in logic_components.iter().enumerate(){
+        if is_input[group] {
+            continue;
+        }
+        let output = literals[group];
+        let group_inputs: Vec<Literal> = logic_component.component_before.iter().map(|input| literals[*input as usize]).collect();
+        match Opcode::from_component_type(logic_component.component_type) {
+            None => solver.add_clause(&[!output]),
+            Some(Opcode::Or) => solver.add_or(output, &group_inputs),
+            Some(Opcode::Nor) => solver.add_or(!output, &group_inputs),
+            Some(Opcode::And) if group_inputs.is_empty() => solver.add_clause(&[!output]),
+            Some(Opcode::And) => solver.add_and(output, &group_inputs),
+            Some(Opcode::Nand) if group_inputs.is_empty() => solver.add_clause(&[output]),
+            Some(Opcode::Nand) => solver.add_and(!output, &group_inputs),
+            Some(Opcode::Xor) => solver.add_xor(output, &group_inputs),
+            Some(Opcode::Xnor) => solver.add_xor(!output, &group_inputs),
+            Some(_) => {
+                let position = logic_component.elements[0];
+                return Err(format!("{} at x{} y{} keeps state, only combinational logic can be checked with sat",
+                    NAMES[logic_component.component_type as usize], position.0, position.1));
+            }
+        }
+    }
+    Ok(circuit.ports.outputs.iter().map(|port| literals[port.group]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_canvas::canvas;
+
+    const REGION: ((i32, i32), (i32, i32)) = ((0, 0), (12, 2));
+    const XOR: [&str; 3] = ["lw--r", "....^w-rL", "lw--r"];
+    const INVERTED_XNOR: [&str; 3] = ["lw--r", "....xw-r!w-rL", "lw--r"];
+    const OR: [&str; 3] = ["lw--r", "....|w-rL", "lw--r"];
+
+    #[test]
+    fn equivalent_circuits(){
+        let (a, b) = (canvas(&XOR), canvas(&INVERTED_XNOR));
+        for force_sat in [false, true] {
+            let equivalence = check((&a, REGION), (&b, REGION), force_sat).unwrap();
+            assert_eq!((equivalence.inputs.len(), equivalence.outputs.len()), (2, 1));
+            assert!(equivalence.counterexample.is_none());
+        }
+    }
+
+    //xor and or only differ when both inputs are on
+    #[test]
+    fn different_circuits_have_a_counterexample(){
+        let (a, b) = (canvas(&XOR), canvas(&OR));
+        for force_sat in [false, true] {
+            let counterexample = check((&a, REGION), (&b, REGION), force_sat).unwrap().counterexample.unwrap();
+            assert_eq!(counterexample.inputs, vec![true, true]);
+            assert_eq!((counterexample.outputs_a, counterexample.outputs_b), (vec![false], vec![true]));
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use crate::content::{HEIGHT, WIDTH, ComponentData};
+use crate::draw::draw_canvas;
+use crate::renderer::SoftwareRenderer;
+
+//renders the cells from region.0 to region.1 (inclusive) with every cell scale x scale pixels big
+pub fn render_region(component_data: &mut ComponentData, region: ((i32, i32), (i32, i32)), sim_view: bool, scale: u32) -> SoftwareRenderer {
+    let mut renderer = SoftwareRenderer::new((region.1.0 - region.0.0 + 1) as u32 * scale, (region.1.1 - region.0.1 + 1) as u32 * scale);
+    let zoom = component_data.zoom;
+    let position_on_screen = component_data.position_on_screen;
+    component_data.zoom = scale as f32 / 2.0;
+    component_data.position_on_screen = (-region.0.0 as f32 * component_data.zoom, -region.0.1 as f32 * component_data.zoom);
+    draw_canvas(component_data, &mut renderer, sim_view);
+    component_data.zoom = zoom;
+    component_data.position_on_screen = position_on_screen;
+    renderer
+}
+
+pub fn whole_canvas() -> ((i32, i32), (i32, i32)) {
+    ((0, 0), (WIDTH as i32 - 1, HEIGHT as i32 - 1))
+}
+
+pub fn save_image(path: &std::path::Path, renderer: &SoftwareRenderer) -> std::io::Result<()> {
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let data = match extension.as_str() {
+        "ppm" => encode_ppm(renderer),
+        "png" => encode_png(renderer),
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("unsupported image format: {}", path.display())))
+    };
+    std::fs::write(path, data)
+}
+
+fn encode_ppm(renderer: &SoftwareRenderer) -> Vec<u8> {
+    let mut data = format!("P6\n{} {}\n255\n", renderer.width, renderer.height).into_bytes();
+    for pixel in renderer.pixels.chunks(4){
+        data.extend_from_slice(&pixel[0..3]);
+    }
+    data
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in data.iter(){
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for byte in data.iter(){
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn push_png_chunk(data: &mut Vec<u8>, chunk_type: &[u8; 4], content: &[u8]){
+    data.extend_from_slice(&(content.len() as u32).to_be_bytes());
+    let start = data.len();
+    data.extend_from_slice(chunk_type);
+    data.extend_from_slice(content);
+    let crc = crc32(&data[start..]);
+    data.extend_from_slice(&crc.to_be_bytes());
+}
+
+//zlib stream made of uncompressed deflate blocks, images of circuits are small enough that this doesn't matter
+fn zlib_store(raw: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        data.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        data.push(if blocks.peek().is_none() { 1 } else { 0 });
+        data.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+    data.extend_from_slice(&adler32(raw).to_be_bytes());
+    data
+}
+
+fn encode_png(renderer: &SoftwareRenderer) -> Vec<u8> {
+    let mut data = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    let mut header = vec![];
+    header.extend_from_slice(&renderer.width.to_be_bytes());
+    header.extend_from_slice(&renderer.height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);//8 bit rgb, no interlacing
+    push_png_chunk(&mut data, b"IHDR", &header);
+    let mut raw = Vec::with_capacity(((renderer.width * 3 + 1) * renderer.height) as usize);
+    for row in renderer.pixels.chunks((renderer.width * 4) as usize){
+        raw.push(0);//no filter
+        for pixel in row.chunks(4){
+            raw.extend_from_slice(&pixel[0..3]);
+        }
+    }
+    push_png_chunk(&mut data, b"IDAT", &zlib_store(&raw));
+    push_png_chunk(&mut data, b"IEND", &[]);
+    data
+}
+
+//frames are compressed as they are added, the palette grows up to 256 colours and then new colours use the nearest entry
+pub struct GifRecorder {
+    width: u32,
+    height: u32,
+    palette: Vec<(u8, u8, u8)>,
+    palette_lookup: std::collections::HashMap<(u8, u8, u8), u8>,
+    frames: Vec<Vec<u8>>,
+}
+
+impl GifRecorder {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self{
+            width,
+            height,
+            palette: vec![],
+            palette_lookup: std::collections::HashMap::new(),
+            frames: vec![],
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn get_palette_index(&mut self, color: (u8, u8, u8)) -> u8 {
+        if let Some(index) = self.palette_lookup.get(&color) {
+            return *index;
+        }
+        let index = if self.palette.len() < 256 {
+            self.palette.push(color);
+            (self.palette.len() - 1) as u8
+        } else {
+            let distance = |entry: &(u8, u8, u8)| (entry.0 as i32 - color.0 as i32).pow(2) + (entry.1 as i32 - color.1 as i32).pow(2) + (entry.2 as i32 - color.2 as i32).pow(2);
+            (0..self.palette.len()).min_by_key(|index| distance(&self.palette[*index])).unwrap() as u8
+        };
+        self.palette_lookup.insert(color, index);
+        index
+    }
+
+    pub fn add_frame(&mut self, renderer: &SoftwareRenderer){
+        let mut indices = Vec::with_capacity((self.width * self.height) as usize);
+        for pixel in renderer.pixels.chunks(4){
+            indices.push(self.get_palette_index((pixel[0], pixel[1], pixel[2])));
+        }
+        self.frames.push(lzw_compress(&indices));
+    }
+
+    //the delay is stored in hundredths of a second and capped at the longest one a gif can hold
+    pub fn encode(&self, delay_ms: u32) -> Result<Vec<u8>, String> {
+        if self.width > u16::MAX as u32 || self.height > u16::MAX as u32 {
+            return Err(format!("a gif can be at most {} pixels wide and high, this one is {}x{}", u16::MAX, self.width, self.height));
+        }
+        let delay = std::cmp::min(delay_ms / 10, u16::MAX as u32) as u16;
+        let mut data = b"GIF89a".to_vec();
+        data.extend_from_slice(&(self.width as u16).to_le_bytes());
+        data.extend_from_slice(&(self.height as u16).to_le_bytes());
+        data.extend_from_slice(&[0xF7, 0, 0]);//global colour table with 256 entries
+        for i in 0..256 {
+            let color = self.palette.get(i).copied().unwrap_or((0, 0, 0));
+            data.extend_from_slice(&[color.0, color.1, color.2]);
+        }
+        data.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        data.extend_from_slice(b"NETSCAPE2.0");
+        data.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);//loop forever
+        for frame in self.frames.iter(){
+            data.extend_from_slice(&[0x21, 0xF9, 0x04, 0x04]);
+            data.extend_from_slice(&delay.to_le_bytes());
+            data.extend_from_slice(&[0x00, 0x00]);
+            data.push(0x2C);
+            data.extend_from_slice(&[0, 0, 0, 0]);
+            data.extend_from_slice(&(self.width as u16).to_le_bytes());
+            data.extend_from_slice(&(self.height as u16).to_le_bytes());
+            data.push(0);
+            data.push(8);//minimum code size
+            for block in frame.chunks(255){
+                data.push(block.len() as u8);
+                data.extend_from_slice(block);
+            }
+            data.push(0);
+        }
+        data.push(0x3B);
+        Ok(data)
+    }
+
+    pub fn save(&self, path: &std::path::Path, delay_ms: u32) -> std::io::Result<()> {
+        let data = self.encode(delay_ms).map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidInput, error))?;
+        std::fs::write(path, data)
+    }
+}
+
+struct LzwWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    buffer_bits: u32,
+    code_size: u32,
+    max_code: u32,
+    next_code: u32,
+    clear: bool,
+}
+
+impl LzwWriter {
+    //the code width grows after the code that uses up the current width is written, same as the decoder expects
+    fn write(&mut self, code: u32){
+        self.buffer |= code << self.buffer_bits;
+        self.buffer_bits += self.code_size;
+        while self.buffer_bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.buffer_bits -= 8;
+        }
+        if self.clear {
+            self.code_size = 9;
+            self.max_code = 511;
+            self.clear = false;
+        } else if self.next_code > self.max_code {
+            self.code_size += 1;
+            self.max_code = if self.code_size == 12 { 4096 } else { (1 << self.code_size) - 1 };
+        }
+    }
+}
+
+//variable width lzw as used by gif, the dictionary is cleared once it reaches 4096 codes
+fn lzw_compress(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    let mut writer = LzwWriter{bytes: vec![], buffer: 0, buffer_bits: 0, code_size: 9, max_code: 511, next_code: END + 1, clear: false};
+    let mut dictionary: std::collections::HashMap<(u32, u8), u32> = std::collections::HashMap::new();
+    writer.write(CLEAR);
+    if let Some(first) = indices.first() {
+        let mut prefix = *first as u32;
+        for index in indices[1..].iter(){
+            if let Some(code) = dictionary.get(&(prefix, *index)) {
+                prefix = *code;
+                continue;
+            }
+            writer.write(prefix);
+            if writer.next_code < 4096 {
+                dictionary.insert((prefix, *index), writer.next_code);
+                writer.next_code += 1;
+            } else {
+                dictionary.clear();
+                writer.next_code = END + 1;
+                writer.clear = true;
+                writer.write(CLEAR);
+            }
+            prefix = *index as u32;
+        }
+        writer.write(prefix);
+    }
+    writer.write(END);
+    if writer.buffer_bits > 0 {
+        writer.bytes.push(writer.buffer as u8);
+    }
+    writer.bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::COLORS;
+    use crate::test_canvas::canvas;
+
+    //a plain gif lzw decoder: the code width grows once the next code doesn't fit, a clear code resets the dictionary
+    fn lzw_decompress(data: &[u8]) -> Vec<u8> {
+        let (clear, end) = (256usize, 257usize);
+        let mut position = 0;
+        let mut read = |width: usize| {
+            let mut code = 0;
+            for bit in 0..width {
+                code |= ((data[position / 8] >> (position % 8)) as usize & 1) << bit;
+                position += 1;
+            }
+            code
+        };
+        let mut output = vec![];
+        let mut dictionary: Vec<Vec<u8>> = vec![];
+        let mut width = 9;
+        let mut previous: Option<Vec<u8>> = None;
+        loop {
+            let code = read(width);
+            if code == clear {
+                dictionary = (0..=255).map(|index| vec![index as u8]).collect();
+                dictionary.extend([vec![], vec![]]);
+                width = 9;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return output;
+            }
+            let entry = match previous.as_ref() {
+                None => dictionary[code].clone(),
+                Some(previous) if code < dictionary.len() => {
+                    let entry = dictionary[code].clone();
+                    if dictionary.len() < 4096 {
+                        dictionary.push([previous.clone(), vec![entry[0]]].concat());
+                    }
+                    entry
+                }
+                Some(previous) => {
+                    assert_eq!(code, dictionary.len(), "code from the future");
+                    let entry = [previous.clone(), vec![previous[0]]].concat();
+                    dictionary.push(entry.clone());
+                    entry
+                }
+            };
+            if dictionary.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            output.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_output_decodes_to_the_input(){
+        let mut seed = 12345u32;
+        let mut random = |colors: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % colors) as u8
+        };
+        //long enough to fill the dictionary several times, with few and with all colours
+        let inputs: Vec<Vec<u8>> = vec![vec![], vec![7], vec![3; 100000], (0..100000).map(|_| random(4)).collect(), (0..100000).map(|_| random(256)).collect(),
+            (0..=255).cycle().take(70000).collect()];
+        for input in inputs {
+            assert!(lzw_decompress(&lzw_compress(&input)) == input, "{} indices", input.len());
+        }
+    }
+
+    //walks the blocks of the file and decodes every frame with the global palette
+    #[test]
+    fn gif_frames_decode_to_the_rendered_pixels(){
+        let mut component_data = canvas(&["lw--r&w-", "....r.!.", "..L+h#cf"]);
+        component_data.compile_scene();
+        let region = ((0, 0), (9, 4));
+        let mut recorder = GifRecorder::new(30, 15);
+        let mut frames = vec![];
+        for _ in 0..3 {
+            let renderer = render_region(&mut component_data, region, true, 3);
+            recorder.add_frame(&renderer);
+            frames.push(renderer.pixels.chunks(4).map(|pixel| (pixel[0], pixel[1], pixel[2])).collect::<Vec<_>>());
+            component_data.update_canvas();
+        }
+        let data = recorder.encode(100).unwrap();
+        assert_eq!(&data[..6], b"GIF89a");
+        let palette: Vec<(u8, u8, u8)> = data[13..13 + 768].chunks(3).map(|color| (color[0], color[1], color[2])).collect();
+        let mut index = 13 + 768;
+        let mut decoded = vec![];
+        while data[index] != 0x3B {
+            let image = data[index] == 0x2C;
+            index += if image { 11 } else { 2 };
+            let mut content = vec![];
+            while data[index] != 0 {
+                content.extend_from_slice(&data[index + 1..index + 1 + data[index] as usize]);
+                index += data[index] as usize + 1;
+            }
+            index += 1;
+            if image {
+                decoded.push(lzw_decompress(&content).iter().map(|entry| palette[*entry as usize]).collect::<Vec<_>>());
+            }
+        }
+        assert!(decoded == frames);
+    }
+
+    #[test]
+    fn gif_dimensions_and_delays_are_checked(){
+        let mut recorder = GifRecorder::new(1, 1);
+        recorder.add_frame(&SoftwareRenderer::new(1, 1));
+        let data = recorder.encode(u32::MAX).unwrap();
+        let delay = data.windows(4).position(|block| block == [0x21, 0xF9, 0x04, 0x04]).unwrap() + 4;
+        assert_eq!(&data[delay..delay + 2], &u16::MAX.to_le_bytes());
+        assert!(GifRecorder::new(65536, 1).encode(100).is_err());
+        assert!(GifRecorder::new(1, 65536).encode(100).is_err());
+        assert!(GifRecorder::new(65535, 65535).encode(100).is_ok());
+    }
+
+    #[test]
+    fn cells_cover_whole_blocks_of_pixels_at_every_scale(){
+        let mut component_data = canvas(&["", ".l!w", "..-&", ".rr"]);
+        let region = ((1, 1), (3, 3));
+        for scale in 1..6 {
+            let renderer = render_region(&mut component_data, region, false, scale);
+            for x in region.0.0..=region.1.0 {
+                for y in region.0.1..=region.1.1 {
+                    let color = COLORS[component_data.array[x as usize][y as usize].component_type as usize].0;
+                    for i in 0..scale {
+                        for j in 0..scale {
+                            assert_eq!(renderer.get_pixel((x - region.0.0) as u32 * scale + i, (y - region.0.1) as u32 * scale + j), color, "x{} y{} at scale {}", x, y, scale);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
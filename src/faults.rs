@@ -0,0 +1,202 @@
+use crate::content::{ComponentData, ComponentType, LANES};
+use crate::region::describe_group;
+use crate::vectors::TestVectors;
+
+pub struct Fault {
+    pub group: usize,
+    pub stuck_at: bool,
+}
+
+//detected holds the first tick an output differed from the fault free circuit, for every fault
+pub struct FaultReport {
+    pub faults: Vec<Fault>,
+    pub detected: Vec<Option<u64>>,
+}
+
+//every group can be stuck at 0 or 1 except comments and crosses, which don't carry a signal of their own,
+//and displays, which show their inputs and not their own value
+pub fn list_faults(component_data: &ComponentData) -> Vec<Fault> {
+    let mut faults = vec![];
+    for (group, logic_component) in component_data.logic_components.iter().enumerate(){
+        if matches!(logic_component.component_type, ComponentType::COMMENT | ComponentType::CROSS | ComponentType::SEVEN_SEGMENT | ComponentType::HEX_DISPLAY) {
+            continue;
+        }
+        faults.push(Fault{group, stuck_at: false});
+        faults.push(Fault{group, stuck_at: true});
+    }
+    faults
+}
+
+//lights, asserts and framebuffers are looked at directly, displays through the inputs that make up their digit
+fn observed_groups(component_data: &ComponentData) -> Vec<usize> {
+    let mut observed = vec![];
+    for (group, logic_component) in component_data.logic_components.iter().enumerate(){
+        match logic_component.component_type {
+            ComponentType::LIGHT | ComponentType::ASSERT | ComponentType::FRAMEBUFFER => observed.push(group),
+            ComponentType::SEVEN_SEGMENT | ComponentType::HEX_DISPLAY => observed.extend(logic_component.component_before.iter().map(|input| *input as usize)),
+            _ => {}
+        }
+    }
+    observed.sort();
+    observed.dedup();
+    observed
+}
+
+//a fresh copy of the canvas compiled the same way, with the group numbers of component_data
+fn compile_copy(component_data: &ComponentData) -> ComponentData {
+    let mut copy = ComponentData::default();
+    for x in 0..copy.array.len(){
+        for y in 0..copy.array[x].len(){
+            copy.array[x][y].component_type = component_data.array[x][y].component_type;
+        }
+    }
+    copy.delays = component_data.delays.clone();
+    copy.set_delay_mode(component_data.delay_mode);
+    copy.compile_scene();
+    copy
+}
+
+//runs the vectors from tick 0, observe gets the copy after every tick and stops the run by returning false
+fn run(copy: &mut ComponentData, vectors: &TestVectors, ticks: u64, mut observe: impl FnMut(&ComponentData) -> bool) -> Result<(), String> {
+    for _ in 0..ticks {
+        vectors.apply(copy)?;
+        copy.update_canvas();
+        if !observe(copy) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+//the canvas is compiled once and every run starts over on it. the run without faults is recorded first,
+//then up to 64 faults share a run, each one forced in its own lane. a fault is detected when an observed
+//output in its lane differs from the run without faults in any tick, a run stops once all of its faults are detected.
+//faults can't be injected into groups merged by the optimization pass, so component_data has to be compiled without it
+pub fn simulate(component_data: &ComponentData, faults: Vec<Fault>, vectors: &TestVectors, ticks: u64) -> Result<FaultReport, String> {
+    let observed = observed_groups(component_data);
+    if observed.is_empty() {
+        return Err("there are no lights, displays, framebuffers or asserts to detect faults at".to_string());
+    }
+    let mut copy = compile_copy(component_data);
+    let mut reference = vec![];
+    run(&mut copy, vectors, ticks, |copy| {
+        reference.push(observed.iter().map(|group| if copy.is_group_enabled(*group) { !0 } else { 0 }).collect::<Vec<u64>>());
+        true
+    })?;
+    let mut detected = vec![None; faults.len()];
+    for (batch, batch_faults) in faults.chunks(LANES).enumerate(){
+        copy.reset_simulation();
+        for (lane, fault) in batch_faults.iter().enumerate(){
+            copy.force_group(fault.group, 1 << lane, Some(fault.stuck_at))?;
+        }
+        let mut undetected = !0 >> (LANES - batch_faults.len());
+        let mut tick = 0;
+        run(&mut copy, vectors, ticks, |copy| {
+            for (group, expected) in observed.iter().zip(reference[tick].iter()){
+                let mut differing = (copy.program.value(*group) ^ expected) & undetected;
+                undetected &= !differing;
+                while differing != 0 {
+                    detected[batch * LANES + differing.trailing_zeros() as usize] = Some(tick as u64);
+                    differing &= differing - 1;
+                }
+            }
+            tick += 1;
+            undetected != 0
+        })?;
+    }
+    Ok(FaultReport{faults, detected})
+}
+
+impl FaultReport {
+    pub fn detected_count(&self) -> usize {
+        self.detected.iter().filter(|tick| tick.is_some()).count()
+    }
+
+    pub fn report(&self, component_data: &ComponentData) -> String {
+        let percentage = if self.faults.is_empty() { 100.0 } else { self.detected_count() as f64 * 100.0 / self.faults.len() as f64 };
+        let mut text = format!("{} of {} faults detected ({:.1}%)\n", self.detected_count(), self.faults.len(), percentage);
+        if self.detected_count() < self.faults.len() {
+            text.push_str("undetected:\n");
+            for (fault, tick) in self.faults.iter().zip(self.detected.iter()){
+                if tick.is_none() {
+                    text.push_str(&format!("  {} stuck at {}\n", describe_group(component_data, fault.group), fault.stuck_at as u8));
+                }
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::DelayMode;
+    use crate::test_canvas::{canvas, group_at};
+
+    //latches into a chain of gates with a dead end, a clock and a framebuffer, more than 64 faults in all
+    const CIRCUIT: [&str; 13] = [
+        "lw-r|w-r!w-r!w-rL",
+        "",
+        "!w-r&w-rL",
+        "....r",
+        "lw---",
+        "",
+        "lw-r^w---",
+        "",
+        "cw-r&w-rL",
+        "....r",
+        "lw---",
+        "",
+        "lw-r!w-r!w-r|w-rL",
+    ];
+
+    fn vectors() -> TestVectors {
+        TestVectors::parse("3 toggle 0 0\n5 toggle 0 4\n9 toggle 0 12\n12 toggle 0 10\n14 toggle 0 0\n20 toggle 0 6\n").unwrap()
+    }
+
+    //every fault in a fresh compile of its own, in lane 0 only
+    fn detect_one_by_one(component_data: &ComponentData, faults: &[Fault], vectors: &TestVectors, ticks: u64) -> Vec<Option<u64>> {
+        let observed = observed_groups(component_data);
+        let outputs = |fault: Option<&Fault>| {
+            let mut copy = compile_copy(component_data);
+            if let Some(fault) = fault {
+                copy.force_group(fault.group, 1, Some(fault.stuck_at)).unwrap();
+            }
+            let mut outputs = vec![];
+            run(&mut copy, vectors, ticks, |copy| {
+                outputs.push(observed.iter().map(|group| copy.is_group_enabled(*group)).collect::<Vec<bool>>());
+                true
+            }).unwrap();
+            outputs
+        };
+        let reference = outputs(None);
+        faults.iter().map(|fault| outputs(Some(fault)).iter().zip(reference.iter()).position(|(a, b)| a != b).map(|tick| tick as u64)).collect()
+    }
+
+    #[test]
+    fn lanes_detect_the_same_faults_as_separate_runs(){
+        for delay_mode in [DelayMode::Unit, DelayMode::Zero, DelayMode::Timed] {
+            let mut component_data = canvas(&CIRCUIT);
+            component_data.set_delay_mode(delay_mode);
+            component_data.compile_scene();
+            let faults = list_faults(&component_data);
+            assert!(faults.len() > LANES);
+            let expected = detect_one_by_one(&component_data, &faults, &vectors(), 40);
+            let report = simulate(&component_data, faults, &vectors(), 40).unwrap();
+            assert!(report.detected == expected, "{}", delay_mode.name());
+            assert!(report.detected_count() > 0 && report.detected_count() < report.faults.len());
+        }
+    }
+
+    //the xor leads nowhere, the and behind the constant and the light of the first chain are seen by the vectors
+    #[test]
+    fn faults_outside_the_tested_logic_are_undetected(){
+        let mut component_data = canvas(&CIRCUIT);
+        component_data.compile_scene();
+        let report = simulate(&component_data, list_faults(&component_data), &vectors(), 40).unwrap();
+        let undetected = |x, y| report.faults.iter().zip(report.detected.iter()).filter(|(fault, tick)| fault.group == group_at(&component_data, x, y) && tick.is_none()).count();
+        assert_eq!(undetected(4, 6), 2);
+        assert_eq!(undetected(4, 2), 0);
+        assert_eq!(undetected(16, 0), 0);
+    }
+}
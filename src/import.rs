@@ -0,0 +1,483 @@
+use crate::content::{HEIGHT, WIDTH, ComponentType, ComponentData, COLORS};
+
+//RGBA pixels, row by row
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub struct ImportReport {
+    pub cells: u32,
+    pub unknown_colors: Vec<((u8, u8, u8), u32)>,
+    pub cropped: bool,
+}
+
+pub fn load_image(path: &std::path::Path) -> Result<Image, String> {
+    let data = std::fs::read(path).map_err(|error| error.to_string())?;
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        decode_png(&data)
+    } else if data.starts_with(b"BM") {
+        decode_bmp(&data)
+    } else if data.starts_with(b"P6") || data.starts_with(b"P3") {
+        decode_ppm(&data)
+    } else {
+        Err(String::from("unknown image format, expected png, bmp or ppm"))
+    }
+}
+
+//every scale x scale block of the image becomes one cell, the colour in the middle of the block is used
+pub fn image_to_canvas(image: &Image, scale: u32, component_data: &mut ComponentData) -> ImportReport {
+    let mut report = ImportReport{cells: 0, unknown_colors: vec![], cropped: false};
+    let width = image.width / scale;
+    let height = image.height / scale;
+    report.cropped = width > WIDTH || height > HEIGHT;
+    for i in 0..std::cmp::min(width, WIDTH) {
+        for j in 0..std::cmp::min(height, HEIGHT) {
+            let index = (((j * scale + scale / 2) * image.width + i * scale + scale / 2) * 4) as usize;
+            let color = (image.pixels[index], image.pixels[index + 1], image.pixels[index + 2]);
+            let component_type = if image.pixels[index + 3] < 128 {
+                ComponentType::NOTHING
+            } else {
+                let (component_type, exact) = get_nearest_component(color);
+                if !exact {
+                    match report.unknown_colors.iter_mut().find(|unknown| unknown.0 == color) {
+                        Some(unknown) => unknown.1 += 1,
+                        None => report.unknown_colors.push((color, 1))
+                    }
+                }
+                component_type
+            };
+            component_data.set_cell(i as usize, j as usize, component_type);
+            if component_type != ComponentType::NOTHING {
+                report.cells += 1;
+            }
+        }
+    }
+    report.unknown_colors.sort_by_key(|unknown| std::cmp::Reverse(unknown.1));
+    report
+}
+
+//nearest off colour, a colour is exact if it is the off or on colour of some component
+fn get_nearest_component(color: (u8, u8, u8)) -> (ComponentType, bool) {
+    let mut best = (ComponentType::NOTHING, i32::MAX);
+    for (i, colors) in COLORS.iter().enumerate() {
+        if colors.0 == color || colors.1 == color {
+            return (ComponentType::from_u32(i as u32), true);
+        }
+        let distance = (colors.0.0 as i32 - color.0 as i32).pow(2) + (colors.0.1 as i32 - color.1 as i32).pow(2) + (colors.0.2 as i32 - color.2 as i32).pow(2);
+        if distance < best.1 {
+            best = (ComponentType::from_u32(i as u32), distance);
+        }
+    }
+    (best.0, false)
+}
+
+fn decode_ppm(data: &[u8]) -> Result<Image, String> {
+    //header: magic, width, height, max value, separated by whitespace, comments start with #
+    let mut fields = vec![];
+    let mut index = 0;
+    while fields.len() < 4 && index < data.len() {
+        if data[index] == b'#' {
+            while index < data.len() && data[index] != b'\n' {
+                index += 1;
+            }
+        } else if data[index].is_ascii_whitespace() {
+            index += 1;
+        } else {
+            let start = index;
+            while index < data.len() && !data[index].is_ascii_whitespace() {
+                index += 1;
+            }
+            fields.push(String::from_utf8_lossy(&data[start..index]).to_string());
+        }
+    }
+    if fields.len() < 4 {
+        return Err(String::from("truncated ppm header"));
+    }
+    let width: u32 = fields[1].parse().map_err(|_| "invalid ppm width")?;
+    let height: u32 = fields[2].parse().map_err(|_| "invalid ppm height")?;
+    let max_value: u32 = fields[3].parse().map_err(|_| "invalid ppm max value")?;
+    if max_value == 0 || max_value > 255 {
+        return Err(String::from("only 8 bit ppm images are supported"));
+    }
+    let samples: Vec<u32> = if fields[0] == "P6" {
+        data.get(index + 1..).unwrap_or(&[]).iter().map(|sample| *sample as u32).collect()
+    } else {
+        String::from_utf8_lossy(&data[index..]).split_whitespace().filter_map(|sample| sample.parse().ok()).collect()
+    };
+    let pixel_count = (width as usize).checked_mul(height as usize).ok_or(String::from("ppm image too big"))?;
+    if samples.len() / 3 < pixel_count {
+        return Err(String::from("truncated ppm data"));
+    }
+    let mut pixels = Vec::with_capacity(pixel_count * 4);
+    for pixel in samples.chunks(3).take(pixel_count) {
+        for sample in pixel.iter() {
+            pixels.push((sample * 255 / max_value) as u8);
+        }
+        pixels.push(255);
+    }
+    Ok(Image{width, height, pixels})
+}
+
+fn read_u16_le(data: &[u8], index: usize) -> Result<u16, String> {
+    data.get(index..index + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])).ok_or(String::from("truncated file"))
+}
+
+fn read_u32_le(data: &[u8], index: usize) -> Result<u32, String> {
+    data.get(index..index + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok_or(String::from("truncated file"))
+}
+
+fn decode_bmp(data: &[u8]) -> Result<Image, String> {
+    let offset = read_u32_le(data, 10)? as usize;
+    let width = read_u32_le(data, 18)? as i32;
+    let height = read_u32_le(data, 22)? as i32;
+    let bits_per_pixel = read_u16_le(data, 28)? as u32;
+    let compression = read_u32_le(data, 30)?;
+    if (bits_per_pixel != 24 && bits_per_pixel != 32) || (compression != 0 && compression != 3) || width <= 0 {
+        return Err(String::from("only uncompressed 24 and 32 bit bmp images are supported"));
+    }
+    let bottom_up = height > 0;
+    let (width, height) = (width as u32, height.unsigned_abs());
+    let stride = (bits_per_pixel as usize * width as usize).div_ceil(32) * 4;
+    if stride.checked_mul(height as usize).and_then(|size| size.checked_add(offset)).is_none_or(|end| data.len() < end) {
+        return Err(String::from("truncated bmp data"));
+    }
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for j in 0..height {
+        let row = if bottom_up { height - 1 - j } else { j } as usize;
+        for i in 0..width as usize {
+            let index = offset + row * stride + i * (bits_per_pixel / 8) as usize;
+            pixels.extend_from_slice(&[data[index + 2], data[index + 1], data[index], 255]);
+        }
+    }
+    Ok(Image{width, height, pixels})
+}
+
+fn decode_png(data: &[u8]) -> Result<Image, String> {
+    let mut index = 8;
+    let mut header = None;
+    let mut palette = vec![];
+    let mut compressed = vec![];
+    while index + 8 <= data.len() {
+        let length = u32::from_be_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]) as usize;
+        let chunk_type = &data[index + 4..index + 8];
+        let content = data.get(index + 8..index + 8 + length).ok_or(String::from("truncated png chunk"))?;
+        match chunk_type {
+            b"IHDR" if content.len() < 13 => return Err(String::from("png header is too short")),
+            b"IHDR" => header = Some((u32::from_be_bytes([content[0], content[1], content[2], content[3]]), u32::from_be_bytes([content[4], content[5], content[6], content[7]]), content[8], content[9], content[12])),
+            b"PLTE" => palette = content.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(content),
+            b"IEND" => break,
+            _ => {}
+        }
+        index += length + 12;
+    }
+    let (width, height, bit_depth, color_type, interlace) = header.ok_or(String::from("png has no header"))?;
+    if bit_depth != 8 || interlace != 0 {
+        return Err(String::from("only 8 bit non interlaced png images are supported"));
+    }
+    let channels = match color_type {
+        0 => 1,
+        2 => 3,
+        3 => 1,
+        4 => 2,
+        6 => 4,
+        _ => return Err(String::from("unknown png color type"))
+    };
+    if compressed.len() < 2 {
+        return Err(String::from("png has no image data"));
+    }
+    let raw = inflate(&compressed[2..])?;
+    let stride = width as usize * channels as usize;
+    if (stride + 1).checked_mul(height as usize).is_none_or(|size| raw.len() < size) {
+        return Err(String::from("truncated png data"));
+    }
+    let mut previous = vec![0u8; stride];
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for j in 0..height as usize {
+        let filter = raw[j * (stride + 1)];
+        let mut row = raw[j * (stride + 1) + 1..(j + 1) * (stride + 1)].to_vec();
+        for i in 0..stride {
+            let left = if i >= channels as usize { row[i - channels as usize] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= channels as usize { previous[i - channels as usize] } else { 0 };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(String::from("unknown png filter"))
+            };
+            row[i] = row[i].wrapping_add(predictor);
+        }
+        for pixel in row.chunks(channels as usize) {
+            match color_type {
+                0 => pixels.extend_from_slice(&[pixel[0], pixel[0], pixel[0], 255]),
+                2 => pixels.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]),
+                3 => {
+                    let entry = palette.get(pixel[0] as usize * 3..pixel[0] as usize * 3 + 3).ok_or(String::from("png palette index out of range"))?;
+                    pixels.extend_from_slice(&[entry[0], entry[1], entry[2], 255]);
+                }
+                4 => pixels.extend_from_slice(&[pixel[0], pixel[0], pixel[0], pixel[1]]),
+                _ => pixels.extend_from_slice(pixel)
+            }
+        }
+        previous = row;
+    }
+    Ok(Image{width, height, pixels})
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit: u32,
+}
+
+impl BitReader<'_> {
+    fn read_bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self.data.get(self.position).ok_or(String::from("truncated deflate stream"))?;
+            value |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.position += 1;
+            }
+        }
+        Ok(value)
+    }
+}
+
+//canonical huffman code, decoded one bit at a time
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths.iter() {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for i in 1..15 {
+            offsets[i + 1] = offsets[i] + counts[i];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Self{counts, symbols}
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..16 {
+            code |= reader.read_bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(String::from("invalid huffman code"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader{data, position: 0, bit: 0};
+    let mut output = vec![];
+    loop {
+        let last = reader.read_bits(1)? == 1;
+        match reader.read_bits(2)? {
+            0 => {
+                if reader.bit != 0 {
+                    reader.bit = 0;
+                    reader.position += 1;
+                }
+                let length = read_u16_le(data, reader.position)? as usize;
+                reader.position += 4;
+                output.extend_from_slice(data.get(reader.position..reader.position + length).ok_or(String::from("truncated deflate stream"))?);
+                reader.position += length;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                for (symbol, length) in lengths.iter_mut().enumerate() {
+                    *length = match symbol { 0..=143 => 8, 144..=255 => 9, 256..=279 => 7, _ => 8 };
+                }
+                inflate_block(&mut reader, &mut output, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let literal_count = reader.read_bits(5)? as usize + 257;
+                let distance_count = reader.read_bits(5)? as usize + 1;
+                let code_length_count = reader.read_bits(4)? as usize + 4;
+                let mut code_lengths = [0u8; 19];
+                for i in 0..code_length_count {
+                    code_lengths[CODE_LENGTH_ORDER[i]] = reader.read_bits(3)? as u8;
+                }
+                let code_length_huffman = Huffman::new(&code_lengths);
+                let mut lengths = vec![];
+                while lengths.len() < literal_count + distance_count {
+                    let symbol = code_length_huffman.decode(&mut reader)?;
+                    let (value, repeat) = match symbol {
+                        0..=15 => (symbol as u8, 1),
+                        16 => (*lengths.last().ok_or(String::from("invalid code lengths"))?, reader.read_bits(2)? + 3),
+                        17 => (0, reader.read_bits(3)? + 3),
+                        _ => (0, reader.read_bits(7)? + 11)
+                    };
+                    for _ in 0..repeat {
+                        lengths.push(value);
+                    }
+                }
+                inflate_block(&mut reader, &mut output, &Huffman::new(&lengths[..literal_count]), &Huffman::new(&lengths[literal_count..literal_count + distance_count]))?;
+            }
+            _ => return Err(String::from("invalid deflate block type"))
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+fn inflate_block(reader: &mut BitReader, output: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol < 256 {
+            output.push(symbol as u8);
+        } else if symbol == 256 {
+            return Ok(());
+        } else {
+            let symbol = symbol - 257;
+            if symbol >= 29 {
+                return Err(String::from("invalid length code"));
+            }
+            let length = LENGTH_BASE[symbol] as usize + reader.read_bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+            let distance_symbol = distances.decode(reader)? as usize;
+            if distance_symbol >= 30 {
+                return Err(String::from("invalid distance code"));
+            }
+            let distance = DISTANCE_BASE[distance_symbol] as usize + reader.read_bits(DISTANCE_EXTRA[distance_symbol] as u32)? as usize;
+            if distance > output.len() {
+                return Err(String::from("distance too far back"));
+            }
+            for _ in 0..length {
+                output.push(output[output.len() - distance]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{render_region, save_image, whole_canvas};
+    use crate::test_canvas::canvas;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    //zlib streams from zlib.compress at level 9, the first one uses a fixed huffman block and the second one a dynamic one
+    #[test]
+    fn inflates_fixed_and_dynamic_huffman_blocks(){
+        assert_eq!(inflate(&hex("78dacb48cdc9c957c8402701680308b1")[2..]).unwrap(), b"hello hello hello hello");
+        let dynamic = hex("78daedd0cb0d80201000d1da969f22200808fd77a2538407138eef34c9c89e873b6f1b9b0955f94b5e6fb8e3034f9cb0c6053bdcb0c70347ac70c616574c2291d0248afc3529ebd57af541f201f0950b10");
+        let expected: Vec<u8> = (0..900).map(|i| ((i % 300 * 7 + i % 300 / 5) % 23 + 65) as u8).collect();
+        assert_eq!(inflate(&dynamic[2..]).unwrap(), expected);
+    }
+
+    fn sample_canvas() -> ComponentData {
+        canvas(&["lw--r&w-", "....r.!.", "..L+h#cf", "x^n|a.s."])
+    }
+
+    fn assert_same_cells(a: &ComponentData, b: &ComponentData){
+        for x in 0..WIDTH as usize {
+            for y in 0..HEIGHT as usize {
+                assert!(a.array[x][y].component_type == b.array[x][y].component_type, "cells differ at x{} y{}", x, y);
+            }
+        }
+    }
+
+    //export writes cells at scale 2 by default and import reads them at scale 2 by default
+    #[test]
+    fn exported_images_import_as_the_same_canvas(){
+        let mut component_data = sample_canvas();
+        let renderer = render_region(&mut component_data, whole_canvas(), false, 2);
+        for extension in ["png", "ppm"] {
+            let path = std::env::temp_dir().join(format!("pc_simulation_round_trip_{}.{}", std::process::id(), extension));
+            save_image(&path, &renderer).unwrap();
+            let image = load_image(&path);
+            std::fs::remove_file(&path).unwrap();
+            let image = image.unwrap();
+            let mut imported = ComponentData::default();
+            let report = image_to_canvas(&image, 2, &mut imported);
+            assert!(report.unknown_colors.is_empty() && !report.cropped);
+            assert_same_cells(&component_data, &imported);
+        }
+    }
+
+    fn small_png() -> Vec<u8> {
+        let mut component_data = sample_canvas();
+        let renderer = render_region(&mut component_data, ((0, 0), (7, 3)), false, 1);
+        let path = std::env::temp_dir().join(format!("pc_simulation_small_{}.png", std::process::id()));
+        save_image(&path, &renderer).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        data
+    }
+
+    #[test]
+    fn truncated_png_files_are_errors(){
+        let data = small_png();
+        assert!(decode_png(&data).is_ok());
+        //the end chunk, the checksum of the data chunk and the adler32 of the zlib stream aren't needed to decode the pixels
+        for length in 0..data.len() - 20 {
+            assert!(decode_png(&data[..length]).is_err(), "{} bytes", length);
+        }
+    }
+
+    #[test]
+    fn malformed_headers_are_errors(){
+        let mut data = small_png();
+        //a header chunk of 4 bytes
+        let short_header = [&data[..8], &[0, 0, 0, 4], b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 0]].concat();
+        assert!(decode_png(&short_header).is_err());
+        //the biggest width and height there are, the image data is far too short for that
+        data[16..24].copy_from_slice(&[0xFF; 8]);
+        assert!(decode_png(&data).is_err());
+        assert!(decode_ppm(b"P6 4294967295 4294967295 255\n").is_err());
+        let mut bmp = vec![0u8; 54];
+        bmp[0..2].copy_from_slice(b"BM");
+        bmp[10..14].copy_from_slice(&54u32.to_le_bytes());
+        bmp[18..22].copy_from_slice(&0x7FFFFFFFu32.to_le_bytes());
+        bmp[22..26].copy_from_slice(&0x7FFFFFFFu32.to_le_bytes());
+        bmp[28..30].copy_from_slice(&32u16.to_le_bytes());
+        assert!(decode_bmp(&bmp).is_err());
+    }
+}
@@ -1,4 +1,26 @@
 pub mod content;
+pub mod font;
+pub mod renderer;
+pub mod draw;
+pub mod export;
+pub mod import;
+pub mod cli;
+pub mod program;
+pub mod simulation;
+pub mod region;
+pub mod truth_table;
+pub mod sat;
+pub mod equivalence;
+pub mod vectors;
+pub mod bmc;
+pub mod critical_path;
+pub mod stats;
+pub mod coverage;
+pub mod faults;
+pub mod analyzer;
+pub mod breakpoints;
+#[cfg(test)]
+mod test_canvas;
 
 extern crate sdl2;
 extern crate stopwatch;
@@ -6,97 +28,137 @@ extern crate stopwatch;
 const MSPT_OPTIONS: [i64; 11] = [9223372036854775807, 1000, 500, 200, 100, 50, 20, 10, 5, 2, 0];
 use std::fmt::format;
 use std::ops::Deref;
-use sdl2::render::WindowCanvas;
-use crate::content::{HEIGHT, SIZE, WIDTH, ComponentType, COLORS, NAMES, Component, ComponentData, MiscData};
-
-
-fn get_color(component_type: ComponentType, enabled: bool) -> (u8, u8, u8){
-    return if enabled {
-        COLORS[component_type as usize].1
-    } else {
-        COLORS[component_type as usize].0
-    }
-}
-
-fn draw_pixel(color: (u8, u8, u8), transform: (i32, i32, u32, u32), canvas: &mut WindowCanvas){
-    if transform.0 + (transform.2 as i32) < 0 || transform.0 > (WIDTH as i32 * 2) || transform.1 + (transform.3 as i32) < 0 || transform.1 > (HEIGHT as i32 * 2){
-        return;
-    }
-    canvas.set_draw_color(sdl2::pixels::Color::RGB(color.0, color.1, color.2));
-    canvas.fill_rect(sdl2::rect::Rect::new(transform.0, transform.1, transform.2, transform.3)).expect("couldn't draw");
-
-}
-
-fn draw_component(x: usize, y: usize, component_type_: ComponentType, turned_on_: bool, component_data: &mut ComponentData, canvas: &mut sdl2::render::WindowCanvas){
-    component_data.array[x][y].component_type = component_type_;
-    let color = get_color(component_type_, turned_on_);
-    draw_pixel(color, (((x as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0).round() as i32, ((y as f32 * component_data.zoom + component_data.position_on_screen.1.round()) * 2.0).round() as i32, (component_data.zoom * 2.0).round() as u32, (component_data.zoom * 2.0).round() as u32), canvas);
-}
-
-fn draw_canvas(component_data: &mut ComponentData, canvas: &mut sdl2::render::WindowCanvas, sim_view: bool){
-    let color = COLORS[0].0;
-    canvas.set_draw_color(sdl2::pixels::Color::RGB(color.0 / 2, color.1 / 2, color.2 / 2));
-    canvas.clear();
-    canvas.set_draw_color(sdl2::pixels::Color::RGB(color.0, color.1, color.2));
-    canvas.fill_rect(sdl2::rect::Rect::new(component_data.position_on_screen.0.round() as i32 * 2, component_data.position_on_screen.1.round() as i32 * 2, (WIDTH as f32 * component_data.zoom * 2.0) as u32, (HEIGHT as f32 * component_data.zoom * 2.0) as u32)).expect("failed to draw");
-    if sim_view {
-        draw_canvas_components(component_data, canvas);
-    }else {
-        draw_canvas_pixels(component_data, canvas);
+use crate::content::{HEIGHT, SIZE, WIDTH, ComponentType, COLORS, ComponentData, MiscData, Label, MAX_DELAY};
+use crate::draw::{get_toolbar_entries, draw_analyzer, draw_breakpoints, draw_canvas, draw_coverage, draw_critical_path, draw_faults, draw_inspector, get_analyzer_tick, is_over_analyzer, draw_status_bar, draw_text, draw_to_paste, draw_toolbar, ANALYZER_HEIGHT, LABEL_COLOR, PANEL_HEIGHT, TOOLBAR_HEIGHT, TOOLBAR_TOP};
+use crate::renderer::{Renderer, SdlRenderer};
+use crate::font::GLYPH_HEIGHT;
+use crate::simulation::SimulationThread;
+
+fn find_label(component_data: &ComponentData, pos: (i32, i32)) -> Option<usize> {
+    for (index, label) in component_data.labels.iter().enumerate(){
+        if pos.0 >= label.position.0 && pos.0 < label.position.0 + font::text_width(&label.text) as i32 &&
+            pos.1 >= label.position.1 && pos.1 < label.position.1 + GLYPH_HEIGHT as i32 {
+            return Some(index);
+        }
     }
+    None
 }
 
-fn draw_canvas_components(component_data: &mut ComponentData, canvas: &mut sdl2::render::WindowCanvas){
-    for i in 0..component_data.logic_components.len(){
-        let color = get_color(component_data.logic_components[i].component_type, component_data.logic_components[i].enabled);
-        canvas.set_draw_color(sdl2::pixels::Color::RGB(color.0, color.1, color.2));
-        for j in 0..component_data.logic_components[i].elements.len(){
-            canvas.fill_rect(sdl2::rect::Rect::new(((component_data.logic_components[i].elements[j].0 as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0).round() as i32,
-                                                        ((component_data.logic_components[i].elements[j].1 as f32 * component_data.zoom + component_data.position_on_screen.1.round()) * 2.0).round() as i32, (component_data.zoom * 2.0).round() as u32, (component_data.zoom * 2.0).round() as u32)).expect("failed to draw rect");
+fn edit_label(event: &sdl2::event::Event, component_data: &mut ComponentData, misc_data: &mut MiscData){
+    let index = misc_data.editing_label.unwrap();
+    match event {
+        sdl2::event::Event::TextInput {text, ..} => {
+            if misc_data.skip_text_input {//text input of the key that started editing
+                misc_data.skip_text_input = false;
+            } else if component_data.labels[index].text.len() + text.len() <= MAX_RECORD_LENGTH {
+                component_data.labels[index].text.push_str(text);
+            }
+        }
+        sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Backspace), ..} => {
+            component_data.labels[index].text.pop();
+        }
+        sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Return), ..} |
+        sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Escape), ..} => {
+            if component_data.labels[index].text.is_empty() {
+                component_data.labels.remove(index);
+            }
+            misc_data.editing_label = None;
         }
+        _ => {}
     }
 }
 
-fn draw_canvas_pixels(component_data: &mut ComponentData, canvas: &mut sdl2::render::WindowCanvas){
-    for i in 0..component_data.array.len(){
-        for j in 0..component_data.array[0].len(){
-            if component_data.array[i][j].component_type != ComponentType::NOTHING {
-                let color = get_color(component_data.array[i][j].component_type, false);
-                draw_pixel(color, (((i as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0).round() as i32, ((j as f32 * component_data.zoom + component_data.position_on_screen.1.round()) * 2.0).round() as i32, (component_data.zoom * 2.0).round() as u32, (component_data.zoom * 2.0).round() as u32), canvas)
+//typing the value of a bus breakpoint, the bus is the latches in the selection
+fn edit_breakpoint_value(event: &sdl2::event::Event, component_data: &mut ComponentData, misc_data: &mut MiscData){
+    let text = misc_data.breakpoint_value.as_mut().unwrap();
+    match event {
+        sdl2::event::Event::TextInput {text: input, ..} => {
+            if misc_data.skip_text_input {
+                misc_data.skip_text_input = false;
+            } else {
+                text.push_str(input);
+            }
+        }
+        sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Backspace), ..} => {
+            text.pop();
+        }
+        sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Return), ..} => {
+            match breakpoints::find_bus(component_data, misc_data.selection).and_then(|groups| Ok((breakpoints::parse_value(text, groups.len())?, groups))) {
+                Ok((value, groups)) => {
+                    let condition = breakpoints::Condition::Bus{groups, value};
+                    println!("breaks when {}", condition.describe(component_data));
+                    component_data.add_breakpoint(condition);
+                }
+                Err(error) => println!("{}", error)
             }
+            misc_data.breakpoint_value = None;
+        }
+        sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Escape), ..} => {
+            misc_data.breakpoint_value = None;
         }
+        _ => {}
     }
 }
 
 pub fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        cli::run_command(&args[1..]);
+        return;
+    }
     let mut component_data = ComponentData::default();
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem.window("pc sim", WIDTH * 2, HEIGHT * 2)
+    let window = video_subsystem.window("pc sim", WIDTH * 2, HEIGHT * 2 + ANALYZER_HEIGHT + PANEL_HEIGHT)
         .position_centered()
         .build()
         .unwrap();
-    let mut canvas = window.into_canvas().build().unwrap();
+    let canvas = window.into_canvas().build().unwrap();
+    let texture_creator = canvas.texture_creator();
+    let mut canvas = SdlRenderer::new(canvas, &texture_creator);
     let mut event_pump = sdl_context.event_pump().unwrap();//code above inits sdl2 somehow, idk what it does
 
-    load_array(&mut component_data);
-    draw_canvas(&mut component_data, & mut canvas, false);//initial draw
+    load_array(&mut component_data, &get_default_path("canvas.dat"));
+    let simulation = SimulationThread::start(component_data, MSPT_OPTIONS[MiscData::default().selected_mspt as usize]);
 
 
-    main_update(&mut canvas, &mut event_pump, &mut component_data);//loop
+    main_update(&mut canvas, &mut event_pump, &simulation);//loop
 
 
-    save_array(&component_data);//end of program
+    let component_data = simulation.stop();
+    save_array(&component_data, &get_default_path("canvas.dat"));//end of program
 }
 
-fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::EventPump, mut component_data: &mut ComponentData){
+fn main_update(canvas: &mut dyn Renderer, event_pump: &mut sdl2::EventPump, simulation: &SimulationThread){
     let mut misc_data = MiscData::default();
+    //the frame is drawn from this copy, the canvas itself is only locked to handle input and update the copy
+    let mut view = ComponentData::default();
+    simulation.lock().sync_view(&mut view, true);
     'running: loop {
+        let mut guard = simulation.lock();
+        let component_data: &mut ComponentData = &mut guard;
+        let mut edited = false;
+        if let Some(index) = component_data.breakpoint_hit.take() {
+            misc_data.selected_mspt = 0;
+            simulation.set_mspt(MSPT_OPTIONS[0]);
+            let condition = &component_data.breakpoints[index].condition;
+            println!("tick {}: paused because {}", component_data.ticks, condition.describe(component_data));
+            misc_data.breakpoint_trigger = condition.groups();
+        }
         misc_data.shift_pressed = event_pump.keyboard_state().is_scancode_pressed(sdl2::keyboard::Scancode::LShift);
         misc_data.control_pressed = event_pump.keyboard_state().is_scancode_pressed(sdl2::keyboard::Scancode::LCtrl);
         let mouse_x = event_pump.mouse_state().x() / 2;
         let mouse_y = event_pump.mouse_state().y() / 2;
         for event in event_pump.poll_iter() {
+            edited |= !matches!(event, sdl2::event::Event::MouseMotion {..});
+            if misc_data.editing_label.is_some() && !matches!(event, sdl2::event::Event::Quit {..}) {
+                edit_label(&event, component_data, &mut misc_data);
+                continue;
+            }
+            if misc_data.breakpoint_value.is_some() && !matches!(event, sdl2::event::Event::Quit {..}) {
+                edit_breakpoint_value(&event, component_data, &mut misc_data);
+                continue;
+            }
             match event {
                 sdl2::event::Event::Quit {..} |
                 sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => {
@@ -108,7 +170,6 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
                 },
                 sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Right), ..} => {
                     misc_data.selected_type = ComponentType::from_u32(misc_data.selected_type as u32 % (ComponentType::NUM_COMPONENTS as u32 - 1) + 1);
-                    println!("{}", NAMES[misc_data.selected_type as usize]);
                 },
                 sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Left), ..} => {
                     if misc_data.selected_type as u32 == 1{
@@ -116,14 +177,24 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
                     }else {
                         misc_data.selected_type = ComponentType::from_u32(misc_data.selected_type as u32 - 1);
                     }
-                    println!("{}", NAMES[misc_data.selected_type as usize]);
                 },
                 sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Space), ..} => {
                     if misc_data.run_sim {
+                        simulation.set_running(false);
                         component_data.clear_compiled_data();
+                        misc_data.critical_path.clear();
+                        misc_data.analyzer_cursor = None;
+                        misc_data.breakpoint_trigger.clear();
                     }else{
                         misc_data.paste.0 = false;
                         component_data.compile_scene();
+                        if let Some(report) = component_data.optimization.as_ref() {
+                            println!("{}", report.summary());
+                        }
+                        for warning in component_data.ignored_input_warnings() {
+                            println!("{}", warning);
+                        }
+                        simulation.set_running(true);
                     }
                     misc_data.run_sim = !misc_data.run_sim;
                 }
@@ -150,19 +221,117 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
                 sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Delete), ..} => {
                     for i in misc_data.selection.0.0..misc_data.selection.1.0 {
                         for j in misc_data.selection.0.1..misc_data.selection.1.1 {
-                            component_data.array[i as usize][j as usize].component_type = ComponentType::NOTHING
+                            component_data.set_cell(i as usize, j as usize, ComponentType::NOTHING)
                         }
                     }
+                    let selection = misc_data.selection;
+                    component_data.labels.retain(|label| label.position.0 < selection.0.0 || label.position.0 >= selection.1.0 || label.position.1 < selection.0.1 || label.position.1 >= selection.1.1);
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::T), ..} if !misc_data.run_sim => {
+                    let pos = component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
+                    misc_data.editing_label = find_label(component_data, pos);
+                    if misc_data.editing_label.is_none() && ComponentData::are_coordinates_in_bounds(pos.0, pos.1) {
+                        component_data.labels.push(Label{position: pos, text: String::new()});
+                        misc_data.editing_label = Some(component_data.labels.len() - 1);
+                    }
+                    misc_data.skip_text_input = misc_data.editing_label.is_some();
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::P), ..} => {
+                    let region = if misc_data.selection.0 != misc_data.selection.1 { misc_data.selection } else { export::whole_canvas() };
+                    let scale = std::cmp::max((component_data.zoom * 2.0).round() as u32, 1);
+                    let renderer = export::render_region(component_data, region, misc_data.run_sim, scale);
+                    let path = get_default_path(&format!("screenshot_{}.png", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+                    match export::save_image(&path, &renderer) {
+                        Ok(_) => println!("saved {}", path.display()),
+                        Err(error) => println!("couldn't save {}: {}", path.display(), error)
+                    }
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::Z), ..} => {
+                    component_data.set_delay_mode(component_data.delay_mode.next());
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::O), ..} => {
+                    component_data.set_optimize(!component_data.optimize);
+                    if let Some(report) = component_data.optimization.as_ref() {
+                        println!("{}", report.summary());
+                    }
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::K), ..} if misc_data.run_sim => {
+                    if !misc_data.critical_path.is_empty() {
+                        misc_data.critical_path.clear();
+                    } else if let Some(path) = critical_path::find(component_data) {
+                        print!("{}", path.report(component_data));
+                        misc_data.critical_path = path.groups;
+                    } else {
+                        println!("there is no path between latches, clocks or framebuffers");
+                    }
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::I), ..} if misc_data.run_sim => {
+                    print!("{}", stats::collect(component_data).report(component_data));
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::H), ..} if misc_data.run_sim => {
+                    print!("{}", coverage::collect(component_data).report(component_data));
+                    component_data.set_heatmap(!component_data.heatmap);
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::B), ..} => {
+                    if misc_data.run_sim && misc_data.control_pressed {
+                        println!("removed {} breakpoints", component_data.breakpoints.len());
+                        component_data.breakpoints.clear();
+                    } else if misc_data.run_sim && misc_data.shift_pressed {
+                        match breakpoints::find_bus(component_data, misc_data.selection) {
+                            Ok(_) => {
+                                misc_data.breakpoint_value = Some(String::new());
+                                misc_data.skip_text_input = true;
+                            }
+                            Err(error) => println!("{}", error)
+                        }
+                    } else if misc_data.run_sim {
+                        cycle_breakpoint(component_data, mouse_x, mouse_y);
+                    }
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::LeftBracket), ..} |
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::RightBracket), ..} => {
+                    let pos = component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
+                    if misc_data.run_sim && ComponentData::are_coordinates_in_bounds(pos.0, pos.1) && component_data.array[pos.0 as usize][pos.1 as usize].belongs_to != -1 {
+                        let group = component_data.array[pos.0 as usize][pos.1 as usize].belongs_to as usize;
+                        let delay = component_data.logic_components[group].delay;
+                        let delay = if matches!(event, sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::LeftBracket), ..}) { delay.saturating_sub(1) } else { delay + 1 };
+                        component_data.set_group_delay(group, delay);
+                    }
+                }
+                sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::G), ..} if !misc_data.run_sim && misc_data.selection.0 != misc_data.selection.1 => {
+                    match truth_table::generate(component_data, misc_data.selection) {
+                        Ok(table) => {
+                            print!("{}", table.to_markdown());
+                            let path = get_default_path(&format!("truth_table_{}.csv", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
+                            match table.save(&path) {
+                                Ok(_) => println!("saved {}", path.display()),
+                                Err(error) => println!("couldn't save {}: {}", path.display(), error)
+                            }
+                        }
+                        Err(error) => println!("couldn't make a truth table: {}", error)
+                    }
                 }
                 sdl2::event::Event::KeyDown {keycode: Some(sdl2::keyboard::Keycode::S), ..} => {
                     misc_data.selected_mspt += 1;
                     misc_data.selected_mspt = misc_data.selected_mspt % 11;
+                    simulation.set_mspt(MSPT_OPTIONS[misc_data.selected_mspt as usize]);
+                    misc_data.breakpoint_trigger.clear();
                 }
                 sdl2::event::Event::MouseButtonDown {mouse_btn: sdl2::mouse::MouseButton::Left, ..} => {
-                    if misc_data.paste.0{
-                        paste_selection(&mut component_data, &mut misc_data.copied_data, misc_data.paste.1.0, misc_data.paste.1.1);
-                    }else {
+                    if is_over_analyzer(mouse_y * 2) {
                         if misc_data.run_sim {
+                            misc_data.analyzer_cursor = get_analyzer_tick(component_data, misc_data.analyzer_zoom, mouse_x * 2);
+                        }
+                    }else if mouse_y >= HEIGHT as i32 {
+                        if let Some(component_type) = get_toolbar_entry(mouse_x * 2, mouse_y * 2) {
+                            misc_data.selected_type = component_type;
+                        }
+                    }else if misc_data.paste.0{
+                        paste_selection(component_data, &mut misc_data.copied_data, misc_data.paste.1.0, misc_data.paste.1.1);
+                    }else {
+                        if misc_data.run_sim && misc_data.control_pressed {
+                            cycle_fault(component_data, mouse_x, mouse_y);
+                        } else if misc_data.run_sim {
                             component_data.click_latch(mouse_x as f32, mouse_y as f32)
                         } else if misc_data.shift_pressed {
                             misc_data.copy = true;
@@ -172,6 +341,13 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
                     }
 
                 }
+                sdl2::event::Event::MouseButtonDown {mouse_btn: sdl2::mouse::MouseButton::Right, ..} if misc_data.run_sim && mouse_y < HEIGHT as i32 => {
+                    toggle_probe(component_data, mouse_x, mouse_y);
+                }
+                sdl2::event::Event::MouseWheel {y, ..} if misc_data.run_sim && is_over_analyzer(mouse_y * 2) => {
+                    let zoom = if y > 0 { misc_data.analyzer_zoom * 2.0 } else { misc_data.analyzer_zoom / 2.0 };
+                    misc_data.analyzer_zoom = zoom.clamp(1.0 / 16.0, 64.0);
+                }
                 sdl2::event::Event::MouseButtonDown {mouse_btn: sdl2::mouse::MouseButton::Middle, ..} => {
                     misc_data.last_mouse_pos.0 = mouse_x;
                     misc_data.last_mouse_pos.1 = mouse_y;
@@ -201,32 +377,26 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
                 _ => {}
             }
         }
-        if misc_data.run_sim /* timed update*/ {
-            if misc_data.stopwatch.elapsed_ms() - misc_data.last_time > MSPT_OPTIONS[misc_data.selected_mspt as usize] {
-                misc_data.last_time = misc_data.stopwatch.elapsed_ms();
-                component_data.update_canvas();
-            }
-
-        } else /*draw mode*/ {
-            if event_pump.mouse_state().is_mouse_button_pressed(sdl2::mouse::MouseButton::Left) {
+        if !misc_data.run_sim /*draw mode*/ {
+            if event_pump.mouse_state().is_mouse_button_pressed(sdl2::mouse::MouseButton::Left) && mouse_y < HEIGHT as i32 {
+                edited = true;
                 let pos = component_data.translate_mouse_pos(event_pump.mouse_state().x() as f32 / 2.0, event_pump.mouse_state().y() as f32 / 2.0);
                 if misc_data.copy{
                     misc_data.selection.1 = pos;
                 }else if !misc_data.paste.0 {
                     for i in std::cmp::max(pos.0 - SIZE, 0)..std::cmp::min(pos.0 + SIZE + 1, WIDTH as i32) {
                         for j in std::cmp::max(pos.1 - SIZE, 0)..std::cmp::min(pos.1 + SIZE + 1, HEIGHT as i32) {
-                            draw_component(i as usize, j as usize, misc_data.selected_type, false, &mut component_data, canvas);
+                            component_data.set_cell(i as usize, j as usize, misc_data.selected_type);
                         }
                     }
                 }
             }
-            if event_pump.mouse_state().is_mouse_button_pressed(sdl2::mouse::MouseButton::Right) {
-                if !misc_data.paste.0 {
-                    let pos = component_data.translate_mouse_pos(event_pump.mouse_state().x() as f32 / 2.0, event_pump.mouse_state().y() as f32 / 2.0);
-                    for i in std::cmp::max(pos.0 - SIZE, 0)..std::cmp::min(pos.0 + SIZE + 1, WIDTH as i32) {
-                        for j in std::cmp::max(pos.1 - SIZE, 0)..std::cmp::min(pos.1 + SIZE + 1, HEIGHT as i32) {
-                            draw_component(i as usize, j as usize, ComponentType::NOTHING, false, &mut component_data, canvas);
-                        }
+            if event_pump.mouse_state().is_mouse_button_pressed(sdl2::mouse::MouseButton::Right) && mouse_y < HEIGHT as i32 && !misc_data.paste.0 {
+                edited = true;
+                let pos = component_data.translate_mouse_pos(event_pump.mouse_state().x() as f32 / 2.0, event_pump.mouse_state().y() as f32 / 2.0);
+                for i in std::cmp::max(pos.0 - SIZE, 0)..std::cmp::min(pos.0 + SIZE + 1, WIDTH as i32) {
+                    for j in std::cmp::max(pos.1 - SIZE, 0)..std::cmp::min(pos.1 + SIZE + 1, HEIGHT as i32) {
+                        component_data.set_cell(i as usize, j as usize, ComponentType::NOTHING);
                     }
                 }
             }
@@ -241,20 +411,37 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
             misc_data.last_mouse_pos.0 = mouse_x;
             misc_data.last_mouse_pos.1 = mouse_y;
         }
+        component_data.sync_view(&mut view, edited);
+        drop(guard);
+
+        let component_data = &mut view;
         draw_canvas(component_data, canvas, misc_data.run_sim);
+        if misc_data.run_sim {
+            draw_critical_path(component_data, &misc_data.critical_path, canvas);
+            draw_faults(component_data, canvas);
+            draw_breakpoints(component_data, &misc_data.breakpoint_trigger, canvas);
+            if component_data.heatmap {
+                draw_coverage(component_data, canvas);
+            }
+        }
         let mut pos = component_data.translate_mouse_pos( mouse_x as f32, mouse_y as f32);
 
+        if let Some(index) = misc_data.editing_label /*draw text cursor*/ {
+            let label = &component_data.labels[index];
+            draw_text("_", ((label.position.0 + font::text_width(&label.text) as i32) as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0, (label.position.1 as f32 * component_data.zoom + component_data.position_on_screen.1) * 2.0, component_data.zoom * 2.0, LABEL_COLOR, canvas);
+        }
+
         if misc_data.paste.0 /* draw stuff to paste (hopefully transparent)*/ {
             misc_data.paste.1 = pos;
-            draw_to_paste(&component_data, canvas, &misc_data.copied_data, misc_data.paste);
+            draw_to_paste(component_data, canvas, &misc_data.copied_data, misc_data.paste);
         }
 
         if !misc_data.run_sim && !misc_data.shift_pressed && !misc_data.paste.0 /*draw drawing cursor square*/ {
             pos.0 = ((pos.0 - SIZE) as f32 * component_data.zoom + component_data.position_on_screen.0) as i32 * 2;
             pos.1 = ((pos.1 - SIZE) as f32 * component_data.zoom + component_data.position_on_screen.1) as i32 * 2;
             let color = COLORS[ComponentType::COMMENT as usize].0;
-            canvas.set_draw_color(sdl2::pixels::Color::RGBA(color.0, color.1, color.2, 100));
-            canvas.draw_rect(sdl2::rect::Rect::new(pos.0, pos.1, (((SIZE * 4) as f32 + 2.0) * component_data.zoom) as u32, (((SIZE * 4) as f32 + 2.0) * component_data.zoom) as u32)).expect("couldn't draw rect");
+            canvas.set_draw_color(color);
+            canvas.draw_rect(pos.0, pos.1, (((SIZE * 4) as f32 + 2.0) * component_data.zoom) as u32, (((SIZE * 4) as f32 + 2.0) * component_data.zoom) as u32);
         } else if !misc_data.run_sim /*draw selection*/ {
             if misc_data.shift_pressed {
                 pos = misc_data.selection.0;
@@ -262,31 +449,88 @@ fn main_update(canvas: &mut sdl2::render::WindowCanvas, event_pump: &mut sdl2::E
             pos.0 = ((pos.0) as f32 * component_data.zoom + component_data.position_on_screen.0) as i32 * 2;
             pos.1 = ((pos.1) as f32 * component_data.zoom + component_data.position_on_screen.1) as i32 * 2;
             let color = COLORS[ComponentType::COMMENT as usize].0;
-            canvas.set_draw_color(sdl2::pixels::Color::RGBA(color.0, color.1, color.2, 100));
-            canvas.draw_rect(sdl2::rect::Rect::new(pos.0, pos.1, (((misc_data.selection.1.0 - misc_data.selection.0.0) as f32 * 2.0 + 2.0) * component_data.zoom) as u32, (((misc_data.selection.1.1 - misc_data.selection.0.1) as f32 * 2.0 + 2.0) * component_data.zoom) as u32)).expect("couldn't draw rect");
+            canvas.set_draw_color(color);
+            canvas.draw_rect(pos.0, pos.1, (((misc_data.selection.1.0 - misc_data.selection.0.0) as f32 * 2.0 + 2.0) * component_data.zoom) as u32, (((misc_data.selection.1.1 - misc_data.selection.0.1) as f32 * 2.0 + 2.0) * component_data.zoom) as u32);
 
         }
 
+        draw_analyzer(component_data, misc_data.analyzer_zoom, misc_data.analyzer_cursor, canvas);
+
+        if misc_data.run_sim && mouse_y < HEIGHT as i32 /*hover inspector*/ {
+            let pos = component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
+            if ComponentData::are_coordinates_in_bounds(pos.0, pos.1) && component_data.array[pos.0 as usize][pos.1 as usize].belongs_to != -1 {
+                draw_inspector(component_data, component_data.array[pos.0 as usize][pos.1 as usize].belongs_to as usize, (mouse_x * 2, mouse_y * 2), canvas);
+            }
+        }
+
+        draw_toolbar(&misc_data, canvas);
+        draw_status_bar(component_data, &misc_data, simulation.ticks_per_second(), component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32), canvas);
         canvas.present();
     }
 }
 
-fn draw_to_paste(component_data: &ComponentData, canvas: &mut sdl2::render::WindowCanvas, copied_data: &Vec<Vec<u8>>, paste: (bool, (i32, i32))) {
-    for i in 0.. copied_data.len(){
-        for j in 0..copied_data[i].len(){
-            if copied_data[i][j] as u32 != ComponentType::NOTHING as u32 {
-                let mut color = COLORS[copied_data[i][j] as usize].0;
-                color.0 = (color.0 as f32 * 3.0/4.0) as u8;
-                color.1 = (color.1 as f32 * 3.0/4.0) as u8;
-                color.2 = (color.2 as f32 * 3.0/4.0) as u8;
-                canvas.set_draw_color(sdl2::pixels::Color::RGBA(color.0, color.1, color.2, 100));
-                canvas.fill_rect(sdl2::rect::Rect::new((((i as i32 + paste.1.0) as f32 * component_data.zoom + component_data.position_on_screen.0) * 2.0).round() as i32, (((j as i32 + paste.1.1) as f32 * component_data.zoom + component_data.position_on_screen.1.round()) * 2.0).round() as i32, (component_data.zoom * 2.0).round() as u32, (component_data.zoom * 2.0).round() as u32)).expect("failed to draw rect");
+fn toggle_probe(component_data: &mut ComponentData, mouse_x: i32, mouse_y: i32){
+    let pos = component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
+    if !ComponentData::are_coordinates_in_bounds(pos.0, pos.1) || component_data.array[pos.0 as usize][pos.1 as usize].belongs_to == -1 {
+        return;
+    }
+    if !component_data.toggle_probe(component_data.array[pos.0 as usize][pos.1 as usize].belongs_to as usize) {
+        println!("the logic analyzer shows up to {} probes", analyzer::MAX_PROBES);
+    }
+}
 
-            }
+//the group under the cursor breaks when it turns on, then when it turns off, then not anymore
+fn cycle_breakpoint(component_data: &mut ComponentData, mouse_x: i32, mouse_y: i32){
+    let pos = component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
+    if !ComponentData::are_coordinates_in_bounds(pos.0, pos.1) || component_data.array[pos.0 as usize][pos.1 as usize].belongs_to == -1 {
+        return;
+    }
+    let group = component_data.array[pos.0 as usize][pos.1 as usize].belongs_to as usize;
+    let existing = component_data.breakpoints.iter().position(|breakpoint| matches!(breakpoint.condition, breakpoints::Condition::Group{group: other, ..} if other == group));
+    let on = match existing.map(|index| component_data.breakpoints.remove(index).condition) {
+        None => Some(true),
+        Some(breakpoints::Condition::Group{on: true, ..}) => Some(false),
+        _ => None
+    };
+    match on {
+        Some(on) => {
+            let condition = breakpoints::Condition::Group{group, on};
+            println!("breaks when {}", condition.describe(component_data));
+            component_data.add_breakpoint(condition);
         }
+        None => println!("removed the breakpoint on {}", region::describe_group(component_data, group))
+    }
+}
+
+//the group under the cursor goes from working to stuck at 0, stuck at 1 and back
+fn cycle_fault(component_data: &mut ComponentData, mouse_x: i32, mouse_y: i32){
+    let pos = component_data.translate_mouse_pos(mouse_x as f32, mouse_y as f32);
+    if !ComponentData::are_coordinates_in_bounds(pos.0, pos.1) || component_data.array[pos.0 as usize][pos.1 as usize].belongs_to == -1 {
+        return;
+    }
+    let group = component_data.array[pos.0 as usize][pos.1 as usize].belongs_to as usize;
+    let value = match component_data.forced_value(group) {
+        None => Some(false),
+        Some(false) => Some(true),
+        Some(true) => None
+    };
+    match component_data.force_group(group, !0, value) {
+        Ok(_) => match value {
+            Some(value) => println!("{} is stuck at {}", region::describe_group(component_data, group), value as u8),
+            None => println!("{} works again", region::describe_group(component_data, group))
+        },
+        Err(error) => println!("{}", error)
     }
 }
 
+fn get_toolbar_entry(x: i32, y: i32) -> Option<ComponentType> {
+    if y < TOOLBAR_TOP as i32 || y >= (TOOLBAR_TOP + TOOLBAR_HEIGHT) as i32 || x < 0 {
+        return None;
+    }
+    let index = get_toolbar_entries().iter().position(|entry| x >= entry.0 && x < entry.0 + entry.1 as i32)?;
+    Some(ComponentType::from_u32(index as u32 + 1))
+}
+
 fn copy_selection(component_data: &ComponentData, selection: &mut ((i32, i32), (i32, i32)), copied_data: &mut Vec<Vec<u8>>) {
     copied_data.resize((selection.1.0 - selection.0.0 + 1) as usize, vec![]);
     for i in 0..(selection.1.0 - selection.0.0 + 1) {
@@ -301,7 +545,7 @@ fn paste_selection(component_data: &mut ComponentData, copied_data: &mut Vec<Vec
     for i in 0.. copied_data.len(){
         for j in 0..copied_data[i].len(){
             if copied_data[i][j] != ComponentType::NOTHING as u8 && (i as i32) + paste_x >= 0 && (i as i32) + paste_x < WIDTH as i32  && (j as i32) + paste_y >= 0 && (j as i32) + paste_y < HEIGHT as i32 {
-                component_data.array[(i as i32 + paste_x) as usize][(j as i32 + paste_y) as usize].component_type = ComponentType::from_u32(copied_data[i][j] as u32);
+                component_data.set_cell((i as i32 + paste_x) as usize, (j as i32 + paste_y) as usize, ComponentType::from_u32(copied_data[i][j] as u32));
             }
         }
     }
@@ -324,35 +568,179 @@ fn prepare_selection(selection: &mut ((i32, i32), (i32, i32))){
     }
 }
 
-fn save_array(component_data: &ComponentData) {
+fn get_default_path(file_name: &str) -> std::path::PathBuf {
     let mut path = std::env::current_exe().unwrap();
     path.pop();
-    path.push("canvas.dat");
+    path.push(file_name);
+    path
+}
 
+//labels and delays are saved in a trailer after the cells. a canvas without them is saved without it, like older versions did
+//the trailer starts with a tag and its version as little endian u16, then come the records
+const TRAILER_TAG: &[u8; 4] = b"RECS";
+const TRAILER_VERSION: u16 = 2;
+//labels can't be typed longer than this, a longer record can only come from a broken file
+const MAX_RECORD_LENGTH: usize = 1 << 20;
+
+//a label is x and y as little endian u16, the text length as little endian u32, then the text
+//other records use the same layout with x = 0xFFFF and the record kind as y so readers can skip kinds they don't know
+//kind 0 is a delay: x and y as little endian u16 and the delay as little endian u32
+fn push_record(data: &mut Vec<u8>, x: u16, y: u16, record: &[u8]){
+    data.extend_from_slice(&x.to_le_bytes());
+    data.extend_from_slice(&y.to_le_bytes());
+    data.extend_from_slice(&(record.len() as u32).to_le_bytes());
+    data.extend_from_slice(record);
+}
 
-    let mut temp_arr: Vec<u8> = vec![];
+fn encode_canvas(component_data: &ComponentData) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![];
     for column in component_data.array.iter(){
         for element in column.iter(){
-            temp_arr.push(element.component_type as u8)
+            data.push(element.component_type as u8)
+        }
+    }
+    let cells = data.len();
+    data.extend_from_slice(TRAILER_TAG);
+    data.extend_from_slice(&TRAILER_VERSION.to_le_bytes());
+    //labels are only placed on the canvas, so their positions fit in a u16
+    for label in component_data.labels.iter(){
+        if ComponentData::are_coordinates_in_bounds(label.position.0, label.position.1) && label.text.len() <= MAX_RECORD_LENGTH {
+            push_record(&mut data, label.position.0 as u16, label.position.1 as u16, label.text.as_bytes());
+        }
+    }
+    for (position, delay) in component_data.delays.iter(){
+        if component_data.array[position.0][position.1].component_type == ComponentType::NOTHING {
+            continue;
         }
+        let mut record = (position.0 as u16).to_le_bytes().to_vec();
+        record.extend_from_slice(&(position.1 as u16).to_le_bytes());
+        record.extend_from_slice(&delay.to_le_bytes());
+        push_record(&mut data, 0xFFFF, 0, &record);
     }
-    std::fs::write(path, temp_arr).expect("couldn't write to file");
+    if data.len() == cells + TRAILER_TAG.len() + 2 {
+        data.truncate(cells);
+    }
+    data
 }
 
-fn load_array(component_data: &mut ComponentData) {
-    let mut path = std::env::current_exe().unwrap();
-    path.pop();
-    path.push("canvas.dat");
-    if !std::path::Path::new(&path).exists(){
-        return;
+fn decode_canvas(component_data: &mut ComponentData, data: &[u8]) -> Result<(), String> {
+    let cells = (WIDTH * HEIGHT) as usize;
+    if data.len() < cells {
+        return Err(format!("the canvas has {} cells instead of {}", data.len(), cells));
     }
-    let temp_arr: Vec<u8> = std::fs::read(path).unwrap();
-    if temp_arr.len() != (WIDTH * HEIGHT) as usize {
-        return;
+    for (x, column) in component_data.array.iter_mut().enumerate(){
+        for (y, element) in column.iter_mut().enumerate(){
+            element.component_type = ComponentType::from_u32(data[x * HEIGHT as usize + y] as u32);
+        }
+    }
+    let trailer = &data[cells..];
+    if trailer.is_empty() {
+        return Ok(());
+    }
+    if trailer.len() < 6 || &trailer[..4] != TRAILER_TAG {
+        return Err("the data after the cells isn't a list of labels and delays".to_string());
     }
-    for column in component_data.array.iter_mut().enumerate(){
-        for mut element in column.1.iter_mut().enumerate(){
-            element.1.component_type = ComponentType::from_u32(temp_arr[(column.0 as u32 * HEIGHT + element.0 as u32) as usize] as u32);
+    let version = u16::from_le_bytes([trailer[4], trailer[5]]);
+    if version != TRAILER_VERSION {
+        return Err(format!("labels and delays are saved in version {}, only version {} can be read", version, TRAILER_VERSION));
+    }
+    let mut index = 6;
+    while index < trailer.len() {
+        if index + 8 > trailer.len() {
+            return Err("the last record is cut off".to_string());
+        }
+        let x = u16::from_le_bytes([trailer[index], trailer[index + 1]]);
+        let y = u16::from_le_bytes([trailer[index + 2], trailer[index + 3]]);
+        let length = u32::from_le_bytes([trailer[index + 4], trailer[index + 5], trailer[index + 6], trailer[index + 7]]) as usize;
+        index += 8;
+        if length > MAX_RECORD_LENGTH || index + length > trailer.len() {
+            return Err(format!("a record is {} bytes long, only {} are left", length, trailer.len() - index));
+        }
+        let record = &trailer[index..index + length];
+        index += length;
+        if x == 0xFFFF {
+            if y == 0 && record.len() == 8 {
+                let position = (u16::from_le_bytes([record[0], record[1]]) as usize, u16::from_le_bytes([record[2], record[3]]) as usize);
+                let delay = u32::from_le_bytes([record[4], record[5], record[6], record[7]]);
+                if ComponentData::are_coordinates_in_bounds(position.0 as i32, position.1 as i32) {
+                    component_data.delays.push((position, std::cmp::min(delay, MAX_DELAY)));
+                }
+            }
+            continue;
         }
+        component_data.labels.push(Label{position: (x as i32, y as i32), text: String::from_utf8_lossy(record).to_string()});
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+fn save_array(component_data: &ComponentData, path: &std::path::Path) {
+    std::fs::write(path, encode_canvas(component_data)).expect("couldn't write to file");
+}
+
+fn load_array(component_data: &mut ComponentData, path: &std::path::Path) {
+    if !path.exists(){
+        return;
+    }
+    let data: Vec<u8> = std::fs::read(path).unwrap();
+    if let Err(error) = decode_canvas(component_data, &data) {
+        println!("couldn't load all of {}: {}", path.display(), error);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_canvas::canvas;
+
+    fn saved() -> ComponentData {
+        let mut component_data = canvas(&["lw--r", "....|w-rL"]);
+        component_data.labels.push(Label{position: (0, 0), text: "INPUT".to_string()});
+        component_data.labels.push(Label{position: (699, 349), text: "ÄÖÜ ".repeat(100)});
+        component_data.delays.push(((4, 1), 7));
+        component_data
+    }
+
+    fn load(data: &[u8]) -> (ComponentData, Result<(), String>) {
+        let mut component_data = ComponentData::default();
+        let result = decode_canvas(&mut component_data, data);
+        (component_data, result)
+    }
+
+    #[test]
+    fn labels_and_delays_are_saved_after_the_cells(){
+        let component_data = saved();
+        let (loaded, result) = load(&encode_canvas(&component_data));
+        assert_eq!(result, Ok(()));
+        assert!(loaded.array.iter().flatten().map(|cell| cell.component_type).eq(component_data.array.iter().flatten().map(|cell| cell.component_type)));
+        let labels = |component_data: &ComponentData| component_data.labels.iter().map(|label| (label.position, label.text.clone())).collect::<Vec<_>>();
+        assert_eq!(labels(&loaded), labels(&component_data));
+        assert_eq!(loaded.delays, component_data.delays);
+    }
+
+    //files of older versions only have the cells
+    #[test]
+    fn canvases_without_a_trailer_load(){
+        let data = encode_canvas(&saved());
+        let (loaded, result) = load(&data[..(WIDTH * HEIGHT) as usize]);
+        assert_eq!(result, Ok(()));
+        assert!(loaded.array[4][1].component_type == ComponentType::OR);
+        assert!(loaded.labels.is_empty() && loaded.delays.is_empty());
+        assert_eq!(encode_canvas(&canvas(&["lw--r"])).len(), (WIDTH * HEIGHT) as usize);
+    }
+
+    #[test]
+    fn broken_trailers_are_errors(){
+        let data = encode_canvas(&saved());
+        let cells = (WIDTH * HEIGHT) as usize;
+        assert!(load(&data[..cells - 1]).1.is_err());
+        assert!(load(&[&data[..cells], b"RECT\x02\x00"].concat()).1.is_err());
+        assert!(load(&[&data[..cells], b"RECS\x03\x00"].concat()).1.is_err());
+        //the first label is INPUT, cut off in its text and in its length
+        assert!(load(&data[..cells + 6 + 8 + 2]).1.is_err());
+        assert!(load(&data[..cells + 6 + 5]).1.is_err());
+        let mut oversize = data[..cells + 6].to_vec();
+        push_record(&mut oversize, 0, 0, &[]);
+        oversize[cells + 6 + 4..cells + 6 + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        let (loaded, result) = load(&[oversize, vec![0; 100]].concat());
+        assert!(result.is_err() && loaded.labels.is_empty());
+    }
+}
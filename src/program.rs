@@ -0,0 +1,576 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::content::{ComponentType, DelayMode, LogicComponent};
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub(crate) enum Opcode {Or, Nor, And, Nand, Xor, Xnor, Clock, Latch, Framebuffer}
+
+impl Opcode {
+    //the output only depends on the inputs, everything else keeps state between ticks
+    pub fn is_combinational(self) -> bool {
+        self != Opcode::Clock && self != Opcode::Latch && self != Opcode::Framebuffer
+    }
+
+    pub fn from_component_type(component_type: ComponentType) -> Option<Opcode> {
+        match component_type {
+            ComponentType::READ_FROM_WIRE | ComponentType::WRITE_TO_WIRE | ComponentType::WIRE => Some(Opcode::Or),
+            ComponentType::OR | ComponentType::LIGHT | ComponentType::SEVEN_SEGMENT | ComponentType::HEX_DISPLAY | ComponentType::ASSERT => Some(Opcode::Or),
+            ComponentType::NOT => Some(Opcode::Nor),
+            ComponentType::AND => Some(Opcode::And),
+            ComponentType::NAND => Some(Opcode::Nand),
+            ComponentType::XOR => Some(Opcode::Xor),
+            ComponentType::XNOR => Some(Opcode::Xnor),
+            ComponentType::CLOCK => Some(Opcode::Clock),
+            ComponentType::LATCH => Some(Opcode::Latch),
+            ComponentType::FRAMEBUFFER => Some(Opcode::Framebuffer),
+            _ => None//comments never change and crosses are never updated
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Instruction {
+    pub opcode: Opcode,
+    pub group: u32,
+    pub inputs: (u32, u32),
+    pub outputs: (u32, u32),
+}
+
+//a group taking its new value in timing mode, the sequence keeps events of the same tick in the order they were scheduled
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Event {
+    pub time: u64,
+    pub sequence: u64,
+    pub group: u32,
+    pub value: u64,
+}
+
+//the order the phases of a tick run in, every phase only reads groups written by an earlier one
+//so the instructions of a phase can be in any order and are sorted by opcode
+pub(crate) const PHASES: usize = 4;
+
+pub(crate) fn get_phase(component_type: ComponentType) -> usize {
+    match component_type {
+        ComponentType::READ_FROM_WIRE => 0,
+        ComponentType::WRITE_TO_WIRE => 2,
+        ComponentType::WIRE => 3,
+        _ => 1
+    }
+}
+
+//the logic graph flattened into one instruction list per phase, inputs and outputs of all groups are in two shared arrays
+//levelized is the whole tick as one list for zero delay mode: clocks, latches and framebuffers first,
+//then everything combinational in topological order so a signal goes through all gates in one tick
+//state, dirty and changed are indexed by group and hold everything that changes while simulating
+//dirty has the lanes where an input of the group changed, latches only toggle in those lanes
+//toggles counts how often lane 0 of every group flipped since compiling, for the heatmap
+//the lanes set in force_mask always hold force_value whatever the group computes, that's how stuck-at faults are injected
+//in timing mode projected is the value a group will have once its pending events happened
+//group_outputs are the groups to wake up when a group changes, for every group including the ones without an instruction
+//alias points every group to the one holding its value, itself unless the optimization pass merged it into another
+#[derive(Default)]
+pub(crate) struct Program {
+    pub phases: [Vec<Instruction>; PHASES],
+    pub levelized: Vec<Instruction>,
+    pub inputs: Vec<u32>,
+    pub outputs: Vec<u32>,
+    pub group_outputs: Vec<(u32, u32)>,
+    pub alias: Vec<u32>,
+    pub followers: Vec<Vec<u32>>,
+    pub delays: Vec<u32>,
+    pub state: Vec<u64>,
+    pub dirty: Vec<u64>,
+    pub changed: Vec<bool>,
+    pub toggles: Vec<u32>,
+    pub force_mask: Vec<u64>,
+    pub force_value: Vec<u64>,
+    pub projected: Vec<u64>,
+    pub events: BinaryHeap<Reverse<Event>>,
+    next_sequence: u64,
+}
+
+impl Program {
+    pub fn compile(logic_components: &[LogicComponent]) -> Self {
+        let mut program = Program{
+            delays: logic_components.iter().map(|logic_component| logic_component.delay).collect(),
+            ..Default::default()
+        };
+        program.build(logic_components);
+        program.reset();
+        program
+    }
+
+    //everything off and dirty like right after compiling, nothing forced and no events, the instructions are kept
+    pub fn reset(&mut self){
+        let groups = self.group_outputs.len();
+        self.state = vec![0; groups];
+        self.dirty = vec![!0; groups];
+        self.changed = vec![false; groups];
+        self.toggles = vec![0; groups];
+        self.force_mask = vec![0; groups];
+        self.force_value = vec![0; groups];
+        self.projected = vec![0; groups];
+        self.events.clear();
+        self.next_sequence = 0;
+    }
+
+    //the instructions straight from the logic graph, the state is kept
+    fn build(&mut self, logic_components: &[LogicComponent]){
+        self.phases = Default::default();
+        self.levelized.clear();
+        self.inputs.clear();
+        self.outputs.clear();
+        self.group_outputs.clear();
+        self.alias = (0..logic_components.len() as u32).collect();
+        self.followers = vec![vec![]; logic_components.len()];
+        for (group, logic_component) in logic_components.iter().enumerate(){
+            let outputs_start = self.outputs.len() as u32;
+            self.outputs.extend_from_slice(&logic_component.component_after);
+            self.group_outputs.push((outputs_start, self.outputs.len() as u32));
+            let opcode = match Opcode::from_component_type(logic_component.component_type) {
+                Some(opcode) => opcode,
+                None => continue
+            };
+            let inputs_start = self.inputs.len() as u32;
+            self.inputs.extend_from_slice(&logic_component.component_before);
+            self.phases[get_phase(logic_component.component_type)].push(Instruction{
+                opcode,
+                group: group as u32,
+                inputs: (inputs_start, self.inputs.len() as u32),
+                outputs: self.group_outputs[group],
+            });
+        }
+        for phase in self.phases.iter_mut(){
+            phase.sort_by_key(|instruction| (instruction.opcode, instruction.group));
+        }
+        self.levelize(logic_components);
+    }
+
+    //merged groups take the value of the group they followed, then everything is rebuilt unoptimized
+    //combinational groups and clocks are evaluated again, groups that were removed may be out of date until then
+    pub fn rebuild(&mut self, logic_components: &[LogicComponent]){
+        for group in 0..self.alias.len(){
+            let alias = self.alias[group] as usize;
+            self.state[group] = self.state[alias];
+            self.projected[group] = self.projected[alias];
+        }
+        self.build(logic_components);
+        for instruction in self.levelized.iter(){
+            if instruction.opcode.is_combinational() || instruction.opcode == Opcode::Clock {
+                self.dirty[instruction.group as usize] = !0;
+            }
+        }
+    }
+
+    pub fn value(&self, group: usize) -> u64 {
+        self.state[self.alias[group] as usize]
+    }
+
+    pub fn forced(&self, group: usize, value: u64) -> u64 {
+        (value & !self.force_mask[group]) | (self.force_value[group] & self.force_mask[group])
+    }
+
+    fn levelize(&mut self, logic_components: &[LogicComponent]){
+        let mut instructions: Vec<Option<Instruction>> = vec![None; logic_components.len()];
+        for phase in 0..PHASES {
+            for instruction in self.phases[phase].iter(){
+                instructions[instruction.group as usize] = Some(*instruction);
+                if !instruction.opcode.is_combinational() {
+                    self.levelized.push(*instruction);
+                }
+            }
+        }
+        self.levelized.sort_by_key(|instruction| (instruction.opcode, instruction.group));
+        let combinational = |group: usize| matches!(instructions[group], Some(instruction) if instruction.opcode.is_combinational());
+        //strongly connected components come out of tarjan in reverse topological order
+        for mut component in find_strongly_connected_components(logic_components, &combinational).into_iter().rev(){
+            if component.len() > 1 {
+                component.sort_by_key(|group| (get_phase(logic_components[*group].component_type), *group));
+            }
+            for group in component {
+                self.levelized.push(instructions[group].unwrap());
+            }
+        }
+    }
+
+    fn fold_inputs(&self, instruction: &Instruction, start: u64, function: impl Fn(u64, u64) -> u64) -> u64 {
+        let mut state = start;
+        for input in self.inputs[instruction.inputs.0 as usize..instruction.inputs.1 as usize].iter(){
+            state = function(state, self.state[*input as usize]);
+        }
+        state
+    }
+
+    //framebuffers need their memory and are evaluated by the caller
+    pub fn evaluate(&self, instruction: &Instruction) -> u64 {
+        self.evaluate_from(instruction, self.state[instruction.group as usize])
+    }
+
+    //current is what clocks and latches switch from, timing mode passes the projected value
+    pub fn evaluate_from(&self, instruction: &Instruction, current: u64) -> u64 {
+        match instruction.opcode {
+            Opcode::Or => self.fold_inputs(instruction, 0, |a, b| a | b),
+            Opcode::Nor => !self.fold_inputs(instruction, 0, |a, b| a | b),
+            Opcode::And => if instruction.inputs.0 == instruction.inputs.1 { 0 } else { self.fold_inputs(instruction, !0, |a, b| a & b) },
+            Opcode::Nand => if instruction.inputs.0 == instruction.inputs.1 { !0 } else { !self.fold_inputs(instruction, !0, |a, b| a & b) },
+            Opcode::Xor => self.fold_inputs(instruction, 0, |a, b| a ^ b),
+            Opcode::Xnor => !self.fold_inputs(instruction, 0, |a, b| a ^ b),
+            Opcode::Clock => !current,
+            Opcode::Latch => current ^ (self.dirty[instruction.group as usize] & self.fold_inputs(instruction, 0, |a, b| a | b)),//toggles in the dirty lanes where any input is on
+            Opcode::Framebuffer => current,
+        }
+    }
+
+    pub fn schedule(&mut self, group: usize, value: u64, time: u64){
+        self.projected[group] = value;
+        self.events.push(Reverse(Event{time, sequence: self.next_sequence, group: group as u32, value}));
+        self.next_sequence += 1;
+    }
+
+    pub fn has_events_until(&self, time: u64) -> bool {
+        self.events.peek().is_some_and(|event| event.0.time <= time)
+    }
+
+    pub fn mark_outputs_dirty(&mut self, instruction: &Instruction, lanes: u64){
+        self.mark_group_outputs_dirty(instruction.group as usize, lanes);
+    }
+
+    pub fn mark_group_outputs_dirty(&mut self, group: usize, lanes: u64){
+        let (start, end) = self.group_outputs[group];
+        for i in start..end {
+            let output = self.outputs[i as usize] as usize;
+            self.dirty[output] |= lanes;
+        }
+    }
+
+    //the value a group settles to if it only depends on groups that never change, comments and crosses are always off
+    fn fold_constant(&self, instruction: &Instruction, constants: &[Option<bool>]) -> Option<bool> {
+        let inputs: Vec<Option<bool>> = self.inputs[instruction.inputs.0 as usize..instruction.inputs.1 as usize].iter().map(|input| constants[*input as usize]).collect();
+        let all_known = inputs.iter().all(|input| input.is_some());
+        let value = match instruction.opcode {
+            Opcode::Or | Opcode::Nor => if inputs.contains(&Some(true)) { Some(true) } else if all_known { Some(false) } else { None },
+            Opcode::And | Opcode::Nand => if inputs.is_empty() || inputs.contains(&Some(false)) { Some(false) } else if all_known { Some(true) } else { None },
+            Opcode::Xor | Opcode::Xnor => if all_known { Some(inputs.iter().filter(|input| **input == Some(true)).count() % 2 == 1) } else { None },
+            _ => None
+        };
+        match instruction.opcode {
+            Opcode::Nor | Opcode::Nand | Opcode::Xnor => value.map(|value| !value),
+            _ => value
+        }
+    }
+
+    //folds constants, merges copies into the group they copy and drops groups no light, display, framebuffer or assert depends on
+    //writers and wires only pass their single input on in the same tick so they are merged in every mode (with delay 0 in timing mode),
+    //readers and double nots only in zero delay mode where passing a signal on takes no time, and never inside loops
+    pub fn optimize(&mut self, logic_components: &[LogicComponent], delay_mode: DelayMode) -> OptimizationReport {
+        self.rebuild(logic_components);
+        let groups = logic_components.len();
+        let mut instructions: Vec<Option<Instruction>> = vec![None; groups];
+        for instruction in self.levelized.iter(){
+            instructions[instruction.group as usize] = Some(*instruction);
+        }
+        let mut report = OptimizationReport{instructions: (self.levelized.len(), 0), ..Default::default()};
+        let mut constants: Vec<Option<bool>> = instructions.iter().map(|instruction| if instruction.is_none() { Some(false) } else { None }).collect();
+        loop {
+            let mut folded = false;
+            for instruction in self.levelized.iter(){
+                let group = instruction.group as usize;
+                if constants[group].is_none() {
+                    if let Some(value) = self.fold_constant(instruction, &constants) {
+                        constants[group] = Some(value);
+                        report.constants += 1;
+                        folded = true;
+                    }
+                }
+            }
+            if !folded {
+                break;
+            }
+        }
+        let combinational = |group: usize| matches!(instructions[group], Some(instruction) if instruction.opcode.is_combinational());
+        let mut in_loop = vec![false; groups];
+        for component in find_strongly_connected_components(logic_components, &combinational){
+            if component.len() > 1 {
+                for group in component {
+                    in_loop[group] = true;
+                }
+            }
+        }
+        let single_input = |instruction: &Instruction| if instruction.inputs.1 - instruction.inputs.0 == 1 { Some(self.inputs[instruction.inputs.0 as usize] as usize) } else { None };
+        let mut alias: Vec<usize> = (0..groups).collect();
+        for instruction in self.levelized.iter(){
+            let group = instruction.group as usize;
+            let Some(input) = single_input(instruction) else {
+                continue;
+            };
+            if constants[group].is_some() {
+                continue;
+            }
+            //stateful groups run first in zero delay mode and see last tick's value of the combinational groups they read,
+            //so a group they read can't be merged into a stateful one
+            let feeds_state = logic_components[group].component_after.iter().any(|after| matches!(instructions[*after as usize], Some(after) if !after.opcode.is_combinational()));
+            let mergeable = |target: usize| !feeds_state || matches!(instructions[target], Some(target) if target.opcode.is_combinational());
+            let zero_delay = delay_mode == DelayMode::Zero && !in_loop[group];
+            match (logic_components[group].component_type, instruction.opcode) {
+                //latches can be toggled between ticks, in unit delay mode a writer keeps that from reaching readers before the next tick
+                (ComponentType::WRITE_TO_WIRE, Opcode::Or) if delay_mode == DelayMode::Unit && logic_components[input].component_type == ComponentType::LATCH => {}
+                (ComponentType::WRITE_TO_WIRE | ComponentType::WIRE, Opcode::Or) if delay_mode != DelayMode::Timed || self.delays[group] == 0 => {
+                    alias[group] = alias[input];
+                    report.merged += 1;
+                }
+                (ComponentType::READ_FROM_WIRE, Opcode::Or) if zero_delay && mergeable(alias[input]) => {
+                    alias[group] = alias[input];
+                    report.merged += 1;
+                }
+                (_, Opcode::Nor) if zero_delay => {
+                    let before = alias[input];
+                    if let Some(not) = instructions[before].filter(|not| not.opcode == Opcode::Nor && !in_loop[before] && constants[before].is_none()) {
+                        if let Some(not_input) = single_input(&not).filter(|not_input| mergeable(alias[*not_input])) {
+                            alias[group] = alias[not_input];
+                            report.double_nots += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        //observability follows the unoptimized graph, a group still shows its value on the canvas when what read it was merged past it,
+        //and the groups merged into it show that value too
+        let kept = |group: usize| instructions[group].is_some() && constants[group].is_none() && alias[group] == group;
+        let mut observable = vec![false; groups];
+        let mut stack: Vec<usize> = (0..groups).filter(|group| instructions[*group].is_some() && matches!(logic_components[*group].component_type,
+            ComponentType::LIGHT | ComponentType::SEVEN_SEGMENT | ComponentType::HEX_DISPLAY | ComponentType::FRAMEBUFFER | ComponentType::ASSERT)).collect();
+        for group in stack.iter(){
+            observable[*group] = true;
+        }
+        while let Some(group) = stack.pop() {
+            let instruction = instructions[group].unwrap();
+            for input in self.inputs[instruction.inputs.0 as usize..instruction.inputs.1 as usize].iter(){
+                let input = *input as usize;
+                if instructions[input].is_some() && !observable[input] {
+                    observable[input] = true;
+                    stack.push(input);
+                }
+            }
+        }
+        let live: Vec<bool> = (0..groups).map(|group| kept(group) && observable[group]).collect();
+        report.unobservable = (0..groups).filter(|group| kept(*group) && !live[*group]).count();
+
+        let mut inputs = vec![];
+        let mut readers: Vec<Vec<u32>> = vec![vec![]; groups];
+        let mut rewritten: Vec<Option<Instruction>> = vec![None; groups];
+        for group in (0..groups).filter(|group| live[*group]){
+            let mut instruction = instructions[group].unwrap();
+            let start = inputs.len() as u32;
+            for input in self.inputs[instruction.inputs.0 as usize..instruction.inputs.1 as usize].iter(){
+                let input = alias[*input as usize];
+                inputs.push(input as u32);
+                if !readers[input].contains(&(group as u32)) {
+                    readers[input].push(group as u32);
+                }
+            }
+            instruction.inputs = (start, inputs.len() as u32);
+            rewritten[group] = Some(instruction);
+        }
+        let mut outputs = vec![];
+        for group in 0..groups {
+            let start = outputs.len() as u32;
+            outputs.extend_from_slice(&readers[group]);
+            self.group_outputs[group] = (start, outputs.len() as u32);
+            if let Some(instruction) = rewritten[group].as_mut() {
+                instruction.outputs = self.group_outputs[group];
+            }
+        }
+        self.inputs = inputs;
+        self.outputs = outputs;
+        for phase in self.phases.iter_mut(){
+            *phase = phase.iter().filter_map(|instruction| rewritten[instruction.group as usize]).collect();
+        }
+        self.levelized = self.levelized.iter().filter_map(|instruction| rewritten[instruction.group as usize]).collect();
+        //removed groups go dark instead of keeping the value they had, only latches can still be toggled
+        self.events.retain(|event| rewritten[event.0.group as usize].is_some());
+        for group in 0..groups {
+            if let (Some(value), Some(_)) = (constants[group], instructions[group]) {
+                self.state[group] = if value { !0 } else { 0 };
+            } else if kept(group) && !live[group] && instructions[group].unwrap().opcode != Opcode::Latch {
+                self.state[group] = 0;
+            }
+            self.projected[group] = self.state[group];
+            if alias[group] != group {
+                self.alias[group] = alias[group] as u32;
+                self.followers[alias[group]].push(group as u32);
+            }
+        }
+        report.instructions.1 = self.levelized.len();
+        report
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct OptimizationReport {
+    pub constants: usize,
+    pub merged: usize,
+    pub double_nots: usize,
+    pub unobservable: usize,
+    pub instructions: (usize, usize),
+}
+
+impl OptimizationReport {
+    pub fn summary(&self) -> String {
+        format!("optimized {} instructions down to {}: {} constants folded, {} copies merged, {} double nots removed, {} groups without an observable output removed",
+            self.instructions.0, self.instructions.1, self.constants, self.merged, self.double_nots, self.unobservable)
+    }
+}
+
+//iterative tarjan over the groups accepted by include, following component_after
+//a loop of gates ends up as one component, its groups are still evaluated once per tick
+pub(crate) fn find_strongly_connected_components(logic_components: &[LogicComponent], include: &dyn Fn(usize) -> bool) -> Vec<Vec<usize>> {
+    const UNVISITED: u32 = u32::MAX;
+    let mut components = vec![];
+    let mut index = vec![UNVISITED; logic_components.len()];
+    let mut low_link = vec![0; logic_components.len()];
+    let mut on_stack = vec![false; logic_components.len()];
+    let mut stack = vec![];
+    let mut next_index = 0;
+    for start in 0..logic_components.len(){
+        if !include(start) || index[start] != UNVISITED {
+            continue;
+        }
+        let mut calls = vec![(start, 0)];
+        index[start] = next_index;
+        low_link[start] = next_index;
+        next_index += 1;
+        stack.push(start);
+        on_stack[start] = true;
+        while let Some(&(group, edge)) = calls.last() {
+            let after = &logic_components[group].component_after;
+            if edge < after.len() {
+                calls.last_mut().unwrap().1 += 1;
+                let next = after[edge] as usize;
+                if !include(next) {
+                    continue;
+                }
+                if index[next] == UNVISITED {
+                    index[next] = next_index;
+                    low_link[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    calls.push((next, 0));
+                } else if on_stack[next] {
+                    low_link[group] = std::cmp::min(low_link[group], index[next]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(caller, _)) = calls.last() {
+                low_link[caller] = std::cmp::min(low_link[caller], low_link[group]);
+            }
+            if low_link[group] == index[group] {
+                let mut component = vec![];
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == group {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::content::{ComponentData, ComponentType, DelayMode};
+    use crate::test_canvas::canvas;
+
+    //a constant and gate, a chain of copies through two nots, a clock, and logic that leads nowhere
+    const MIXED: [&str; 13] = [
+        "lw-r|w-r!w-r!w-rL",
+        "",
+        "!w-r&w-rL",
+        "....r",
+        "lw---",
+        "",
+        "lw-r^w---",
+        "",
+        "cw-r&w-rL",
+        "....r",
+        "lw---",
+        "",
+        "lw-r!w-r!w-r|w-",
+    ];
+    const LATCHES: [(usize, usize); 5] = [(0, 0), (0, 4), (0, 6), (0, 10), (0, 12)];
+
+    //groups that no light can see
+    fn unobservable(component_data: &ComponentData) -> Vec<bool> {
+        let logic_components = &component_data.logic_components;
+        let mut observable: Vec<bool> = logic_components.iter().map(|logic_component| logic_component.component_type == ComponentType::LIGHT).collect();
+        let mut stack: Vec<usize> = (0..observable.len()).filter(|group| observable[*group]).collect();
+        while let Some(group) = stack.pop() {
+            for input in logic_components[group].component_before.iter(){
+                if !observable[*input as usize] {
+                    observable[*input as usize] = true;
+                    stack.push(*input as usize);
+                }
+            }
+        }
+        observable.iter().map(|observable| !observable).collect()
+    }
+
+    //runs the canvas with and without the pass and toggles the same latches,
+    //the pass is switched on in tick optimize_at and off again 100 ticks later
+    fn compare(delay_mode: DelayMode, optimize_at: u64){
+        let mut plain = canvas(&MIXED);
+        plain.compile_scene();
+        plain.set_delay_mode(delay_mode);
+        let mut optimized = canvas(&MIXED);
+        optimized.compile_scene();
+        optimized.set_delay_mode(delay_mode);
+        let dead = unobservable(&plain);
+        let mut seed = 7u32;
+        for tick in 0..optimize_at + 150 {
+            if tick == optimize_at {
+                optimized.set_optimize(true);
+                let report = optimized.optimization.as_ref().unwrap();
+                assert!(report.constants > 0 && report.merged > 0 && report.unobservable > 0);
+            }
+            if tick == optimize_at + 100 {
+                optimized.set_optimize(false);
+            }
+            //constant signals are on from the start with the pass, without it they need a few ticks, so do removed groups after it
+            if tick >= 10 {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                for (bit, latch) in LATCHES.iter().enumerate(){
+                    if seed >> (16 + bit) & 3 == 0 {
+                        plain.toggle_latch(latch.0, latch.1);
+                        optimized.toggle_latch(latch.0, latch.1);
+                    }
+                }
+            }
+            plain.update_canvas();
+            optimized.update_canvas();
+            if tick < optimize_at + 10 || (tick >= optimize_at + 100 && tick < optimize_at + 110) {
+                continue;
+            }
+            for (group, dead) in dead.iter().enumerate(){
+                //removed groups are dark unless they show a latch, which can still be toggled
+                let source = optimized.program.alias[group] as usize;
+                let shown = !dead || plain.logic_components[source].component_type == ComponentType::LATCH || optimized.optimization.is_none();
+                assert_eq!(optimized.is_group_enabled(group), shown && plain.is_group_enabled(group), "{}, tick {}, group {}", delay_mode.name(), tick, group);
+            }
+        }
+    }
+
+    #[test]
+    fn optimized_programs_show_the_same_values(){
+        for delay_mode in [DelayMode::Unit, DelayMode::Zero, DelayMode::Timed] {
+            compare(delay_mode, 0);
+            compare(delay_mode, 50);
+        }
+    }
+}
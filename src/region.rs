@@ -0,0 +1,81 @@
+use crate::content::{ComponentData, ComponentType, NAMES};
+
+pub struct Port {
+    pub group: usize,
+    pub name: String,
+}
+
+pub struct Ports {
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+}
+
+//copies the cells from region.0 to region.1 (inclusive) onto an empty canvas at the same positions and compiles only them
+pub fn compile_region(component_data: &ComponentData, region: ((i32, i32), (i32, i32))) -> ComponentData {
+    let mut region_data = ComponentData::default();
+    for x in region.0.0..=region.1.0 {
+        for y in region.0.1..=region.1.1 {
+            region_data.array[x as usize][y as usize].component_type = component_data.array[x as usize][y as usize].component_type;
+        }
+    }
+    region_data.compile_scene();
+    region_data
+}
+
+//the component and the position of the first cell of a group, used to name groups in reports
+pub fn describe_group(component_data: &ComponentData, group: usize) -> String {
+    let logic_component = &component_data.logic_components[group];
+    let position = logic_component.elements[0];
+    format!("{} x{} y{}", NAMES[logic_component.component_type as usize], position.0, position.1)
+}
+
+fn is_in_region(region: ((i32, i32), (i32, i32)), x: i32, y: i32) -> bool {
+    x >= region.0.0 && x <= region.1.0 && y >= region.0.1 && y <= region.1.1
+}
+
+//a group at the edge of the region that continues into wiring outside of it
+fn crosses_edge(component_data: &ComponentData, region: ((i32, i32), (i32, i32)), elements: &[(usize, usize)]) -> bool {
+    let sides: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+    for element in elements.iter(){
+        for side in sides {
+            let x = element.0 as i32 + side.0;
+            let y = element.1 as i32 + side.1;
+            if is_in_region(region, x, y) || !ComponentData::are_coordinates_in_bounds(x, y) {
+                continue;
+            }
+            match component_data.array[x as usize][y as usize].component_type {
+                ComponentType::WIRE | ComponentType::CROSS | ComponentType::WRITE_TO_WIRE | ComponentType::READ_FROM_WIRE => return true,
+                _ => {}
+            }
+        }
+    }
+    false
+}
+
+//inputs are latches nothing drives and readers or wires coming in from outside the region without a writer inside,
+//outputs are lights, asserts and writers or wires that leave the region. both are ordered by position, column first
+pub fn find_ports(component_data: &ComponentData, region: ((i32, i32), (i32, i32)), region_data: &ComponentData) -> Ports {
+    let mut ports = Ports{inputs: vec![], outputs: vec![]};
+    for (group, logic_component) in region_data.logic_components.iter().enumerate(){
+        let driven = !logic_component.component_before.is_empty();
+        let read = !logic_component.component_after.is_empty();
+        let (is_input, is_output) = match logic_component.component_type {
+            ComponentType::LATCH => (!driven, false),
+            ComponentType::LIGHT | ComponentType::ASSERT => (false, true),
+            ComponentType::READ_FROM_WIRE => (!driven && crosses_edge(component_data, region, &logic_component.elements), false),
+            ComponentType::WRITE_TO_WIRE => (false, !read && crosses_edge(component_data, region, &logic_component.elements)),
+            ComponentType::WIRE => {
+                let crosses = crosses_edge(component_data, region, &logic_component.elements);
+                (!driven && read && crosses, driven && crosses)
+            }
+            _ => (false, false)
+        };
+        let port = Port{group, name: describe_group(region_data, group)};
+        if is_input {
+            ports.inputs.push(port);
+        } else if is_output {
+            ports.outputs.push(port);
+        }
+    }
+    ports
+}
@@ -0,0 +1,183 @@
+//one pixel per canvas cell, drawn scaled up to the screen in one go
+pub struct CellImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub dirty: Option<((u32, u32), (u32, u32))>,
+}
+
+impl CellImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self{
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+            dirty: Some(((0, 0), (width - 1, height - 1))),
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: (u8, u8, u8)){
+        let index = ((y * self.width + x) * 4) as usize;
+        self.pixels[index] = color.0;
+        self.pixels[index + 1] = color.1;
+        self.pixels[index + 2] = color.2;
+        self.pixels[index + 3] = 255;
+        self.dirty = match self.dirty {
+            Some((min, max)) => Some(((std::cmp::min(min.0, x), std::cmp::min(min.1, y)), (std::cmp::max(max.0, x), std::cmp::max(max.1, y)))),
+            None => Some(((x, y), (x, y)))
+        };
+    }
+}
+
+pub trait Renderer {
+    fn size(&self) -> (u32, u32);
+    fn set_draw_color(&mut self, color: (u8, u8, u8));
+    fn clear(&mut self);
+    fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32);
+    fn draw_rect(&mut self, x: i32, y: i32, width: u32, height: u32);
+    fn draw_cell_image(&mut self, image: &mut CellImage, x: i32, y: i32, width: u32, height: u32);
+    fn present(&mut self);
+}
+
+//keeps the cell image in a streaming texture and only uploads the part that changed
+pub struct SdlRenderer<'a> {
+    pub canvas: sdl2::render::WindowCanvas,
+    texture_creator: &'a sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+    texture: Option<sdl2::render::Texture<'a>>,
+}
+
+impl<'a> SdlRenderer<'a> {
+    pub fn new(canvas: sdl2::render::WindowCanvas, texture_creator: &'a sdl2::render::TextureCreator<sdl2::video::WindowContext>) -> Self {
+        Self{
+            canvas,
+            texture_creator,
+            texture: None,
+        }
+    }
+}
+
+impl Renderer for SdlRenderer<'_> {
+    fn size(&self) -> (u32, u32) {
+        self.canvas.output_size().expect("couldn't get canvas size")
+    }
+
+    fn set_draw_color(&mut self, color: (u8, u8, u8)) {
+        self.canvas.set_draw_color(sdl2::pixels::Color::RGB(color.0, color.1, color.2));
+    }
+
+    fn clear(&mut self) {
+        self.canvas.clear();
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.canvas.fill_rect(sdl2::rect::Rect::new(x, y, width, height)).expect("failed to draw rect");
+    }
+
+    fn draw_rect(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.canvas.draw_rect(sdl2::rect::Rect::new(x, y, width, height)).expect("failed to draw rect");
+    }
+
+    fn draw_cell_image(&mut self, image: &mut CellImage, x: i32, y: i32, width: u32, height: u32) {
+        if self.texture.is_none() {
+            self.texture = Some(self.texture_creator.create_texture_streaming(sdl2::pixels::PixelFormatEnum::RGBA32, image.width, image.height).expect("couldn't create texture"));
+            image.dirty = Some(((0, 0), (image.width - 1, image.height - 1)));
+        }
+        let texture = self.texture.as_mut().unwrap();
+        if let Some((min, max)) = image.dirty {
+            let offset = ((min.1 * image.width + min.0) * 4) as usize;
+            texture.update(sdl2::rect::Rect::new(min.0 as i32, min.1 as i32, max.0 - min.0 + 1, max.1 - min.1 + 1), &image.pixels[offset..], (image.width * 4) as usize).expect("couldn't update texture");
+            image.dirty = None;
+        }
+        self.canvas.copy(texture, None, sdl2::rect::Rect::new(x, y, width, height)).expect("couldn't draw texture");
+    }
+
+    fn present(&mut self) {
+        self.canvas.present();
+    }
+}
+
+//draws into an RGBA buffer in memory, used for screenshots and headless runs
+pub struct SoftwareRenderer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    color: (u8, u8, u8),
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self{
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+            color: (0, 0, 0),
+        }
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+        let index = ((y * self.width + x) * 4) as usize;
+        (self.pixels[index], self.pixels[index + 1], self.pixels[index + 2])
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_draw_color(&mut self, color: (u8, u8, u8)) {
+        self.color = color;
+    }
+
+    fn clear(&mut self) {
+        self.fill_rect(0, 0, self.width, self.height);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        let x_start = std::cmp::max(x, 0) as i64;
+        let y_start = std::cmp::max(y, 0) as i64;
+        let x_end = std::cmp::min(x as i64 + width as i64, self.width as i64);
+        let y_end = std::cmp::min(y as i64 + height as i64, self.height as i64);
+        for j in y_start..y_end {
+            for i in x_start..x_end {
+                let index = ((j * self.width as i64 + i) * 4) as usize;
+                self.pixels[index] = self.color.0;
+                self.pixels[index + 1] = self.color.1;
+                self.pixels[index + 2] = self.color.2;
+                self.pixels[index + 3] = 255;
+            }
+        }
+    }
+
+    fn draw_rect(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.fill_rect(x, y, width, 1);
+        self.fill_rect(x, y + height as i32 - 1, width, 1);
+        self.fill_rect(x, y, 1, height);
+        self.fill_rect(x + width as i32 - 1, y, 1, height);
+    }
+
+    //always draws the whole image, the dirty area is left for the sdl renderer
+    fn draw_cell_image(&mut self, image: &mut CellImage, x: i32, y: i32, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let x_start = std::cmp::max(x, 0) as i64;
+        let y_start = std::cmp::max(y, 0) as i64;
+        let x_end = std::cmp::min(x as i64 + width as i64, self.width as i64);
+        let y_end = std::cmp::min(y as i64 + height as i64, self.height as i64);
+        for j in y_start..y_end {
+            let source_y = (j - y as i64) * image.height as i64 / height as i64;
+            for i in x_start..x_end {
+                let source_x = (i - x as i64) * image.width as i64 / width as i64;
+                let source = ((source_y * image.width as i64 + source_x) * 4) as usize;
+                let index = ((j * self.width as i64 + i) * 4) as usize;
+                self.pixels[index..index + 4].copy_from_slice(&image.pixels[source..source + 4]);
+            }
+        }
+    }
+
+    fn present(&mut self) {}
+}
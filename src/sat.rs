@@ -0,0 +1,442 @@
+//a small cdcl sat solver: two watched literals, first uip clause learning, vsids style variable activity and restarts
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Literal(u32);
+
+impl Literal {
+    pub fn new(var: usize, positive: bool) -> Self {
+        Literal((var as u32) << 1 | !positive as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::ops::Not for Literal {
+    type Output = Literal;
+
+    fn not(self) -> Literal {
+        Literal(self.0 ^ 1)
+    }
+}
+
+const TRUE: i8 = 1;
+const FALSE: i8 = -1;
+const UNASSIGNED: i8 = 0;
+
+fn literal_value(assignment: &[i8], literal: Literal) -> i8 {
+    if literal.is_positive() { assignment[literal.var()] } else { -assignment[literal.var()] }
+}
+
+//activities are positive so their bit patterns sort like the numbers
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct HeapEntry(u64, usize);
+
+#[derive(Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Literal>>,
+    watches: Vec<Vec<usize>>,
+    units: Vec<Literal>,
+    assignment: Vec<i8>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    seen: Vec<bool>,
+    trail: Vec<Literal>,
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    activity_increment: f64,
+    heap: std::collections::BinaryHeap<HeapEntry>,
+    unsatisfiable: bool,
+    pub conflicts: u64,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self{
+            activity_increment: 1.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_var(&mut self) -> Literal {
+        let var = self.assignment.len();
+        self.assignment.push(UNASSIGNED);
+        self.level.push(0);
+        self.reason.push(None);
+        self.phase.push(false);
+        self.seen.push(false);
+        self.activity.push(0.0);
+        self.watches.push(vec![]);
+        self.watches.push(vec![]);
+        self.heap.push(HeapEntry(0f64.to_bits(), var));
+        Literal::new(var, true)
+    }
+
+    pub fn var_count(&self) -> usize {
+        self.assignment.len()
+    }
+
+    //only before solving
+    pub fn add_clause(&mut self, literals: &[Literal]){
+        let mut clause: Vec<Literal> = vec![];
+        for literal in literals.iter(){
+            if clause.contains(&!*literal) {
+                return;//always true
+            }
+            if !clause.contains(literal) {
+                clause.push(*literal);
+            }
+        }
+        match clause.len() {
+            0 => self.unsatisfiable = true,
+            1 => self.units.push(clause[0]),
+            _ => { self.attach_clause(clause); }
+        }
+    }
+
+    fn attach_clause(&mut self, clause: Vec<Literal>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    //the value of a variable in the model found by solve
+    pub fn value(&self, literal: Literal) -> bool {
+        literal_value(&self.assignment, literal) == TRUE
+    }
+
+    //tseitin encodings of gates, output is equivalent to the gate applied to the inputs
+    pub fn add_or(&mut self, output: Literal, inputs: &[Literal]){
+        let mut clause = vec![!output];
+        for input in inputs.iter(){
+            self.add_clause(&[output, !*input]);
+            clause.push(*input);
+        }
+        self.add_clause(&clause);
+    }
+
+    pub fn add_and(&mut self, output: Literal, inputs: &[Literal]){
+        let mut clause = vec![output];
+        for input in inputs.iter(){
+            self.add_clause(&[!output, *input]);
+            clause.push(!*input);
+        }
+        self.add_clause(&clause);
+    }
+
+    //a chain of two input xors, with no inputs the output is off
+    pub fn add_xor(&mut self, output: Literal, inputs: &[Literal]){
+        let Some((last, rest)) = inputs.split_last() else {
+            self.add_clause(&[!output]);
+            return;
+        };
+        let Some((first, middle)) = rest.split_first() else {
+            self.add_clause(&[!output, *last]);
+            self.add_clause(&[output, !*last]);
+            return;
+        };
+        let mut accumulator = *first;
+        for input in middle.iter(){
+            let next = self.new_var();
+            self.add_xor2(next, accumulator, *input);
+            accumulator = next;
+        }
+        self.add_xor2(output, accumulator, *last);
+    }
+
+    fn add_xor2(&mut self, output: Literal, a: Literal, b: Literal){
+        self.add_clause(&[!output, a, b]);
+        self.add_clause(&[!output, !a, !b]);
+        self.add_clause(&[output, !a, b]);
+        self.add_clause(&[output, a, !b]);
+    }
+
+    fn enqueue(&mut self, literal: Literal, reason: Option<usize>){
+        self.assignment[literal.var()] = if literal.is_positive() { TRUE } else { FALSE };
+        self.level[literal.var()] = self.trail_limits.len();
+        self.reason[literal.var()] = reason;
+        self.trail.push(literal);
+    }
+
+    //returns the clause that became false, if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_literal = !self.trail[self.propagated];
+            self.propagated += 1;
+            let mut watchers = std::mem::take(&mut self.watches[false_literal.index()]);
+            let mut i = 0;
+            while i < watchers.len() {
+                let clause_index = watchers[i];
+                let clause = &mut self.clauses[clause_index];
+                if clause[0] == false_literal {
+                    clause.swap(0, 1);
+                }
+                if literal_value(&self.assignment, clause[0]) == TRUE {
+                    i += 1;
+                    continue;
+                }
+                let mut moved = false;
+                for k in 2..clause.len() {
+                    if literal_value(&self.assignment, clause[k]) != FALSE {
+                        clause.swap(1, k);
+                        self.watches[clause[1].index()].push(clause_index);
+                        watchers.swap_remove(i);
+                        moved = true;
+                        break;
+                    }
+                }
+                if moved {
+                    continue;
+                }
+                let first = clause[0];
+                if literal_value(&self.assignment, first) == FALSE {
+                    self.watches[false_literal.index()] = watchers;
+                    self.propagated = self.trail.len();
+                    return Some(clause_index);
+                }
+                self.enqueue(first, Some(clause_index));
+                i += 1;
+            }
+            self.watches[false_literal.index()] = watchers;
+        }
+        None
+    }
+
+    fn bump_activity(&mut self, var: usize){
+        self.activity[var] += self.activity_increment;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut(){
+                *activity *= 1e-100;
+            }
+            self.activity_increment *= 1e-100;
+            self.rebuild_heap();
+        } else {
+            self.heap.push(HeapEntry(self.activity[var].to_bits(), var));
+        }
+    }
+
+    fn rebuild_heap(&mut self){
+        self.heap = (0..self.var_count()).filter(|var| self.assignment[*var] == UNASSIGNED).map(|var| HeapEntry(self.activity[var].to_bits(), var)).collect();
+    }
+
+    //first unique implication point: the learnt clause has exactly one literal from the conflict level, it goes first
+    fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize) {
+        let current_level = self.trail_limits.len();
+        let mut learnt = vec![Literal(0)];
+        let mut counter = 0;
+        let mut clause_index = conflict;
+        let mut index = self.trail.len();
+        let mut skip_first = false;
+        loop {
+            for k in (skip_first as usize)..self.clauses[clause_index].len() {
+                let literal = self.clauses[clause_index][k];
+                let var = literal.var();
+                if self.seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.bump_activity(var);
+                if self.level[var] == current_level {
+                    counter += 1;
+                } else {
+                    learnt.push(literal);
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let literal = self.trail[index];
+            self.seen[literal.var()] = false;
+            counter -= 1;
+            if counter == 0 {
+                learnt[0] = !literal;
+                break;
+            }
+            clause_index = self.reason[literal.var()].unwrap();
+            skip_first = true;
+        }
+        for literal in learnt.iter(){
+            self.seen[literal.var()] = false;
+        }
+        let mut backjump_level = 0;
+        for k in 1..learnt.len() {
+            if self.level[learnt[k].var()] > backjump_level {
+                backjump_level = self.level[learnt[k].var()];
+                learnt.swap(1, k);
+            }
+        }
+        (learnt, backjump_level)
+    }
+
+    fn backtrack(&mut self, level: usize){
+        if self.trail_limits.len() <= level {
+            return;
+        }
+        for literal in self.trail.drain(self.trail_limits[level]..){
+            let var = literal.var();
+            self.assignment[var] = UNASSIGNED;
+            self.phase[var] = literal.is_positive();
+            self.heap.push(HeapEntry(self.activity[var].to_bits(), var));
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    fn pick_branch_var(&mut self) -> Option<usize> {
+        while let Some(HeapEntry(_, var)) = self.heap.pop() {
+            if self.assignment[var] == UNASSIGNED {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    //true if the clauses can all be satisfied, the model is then read with value
+    pub fn solve(&mut self) -> bool {
+        if self.unsatisfiable {
+            return false;
+        }
+        for i in 0..self.units.len(){
+            let unit = self.units[i];
+            match literal_value(&self.assignment, unit) {
+                FALSE => return false,
+                UNASSIGNED => self.enqueue(unit, None),
+                _ => {}
+            }
+        }
+        let mut restart_limit = 100;
+        let mut conflicts_since_restart = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflicts += 1;
+                conflicts_since_restart += 1;
+                if self.trail_limits.is_empty() {
+                    self.unsatisfiable = true;
+                    return false;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let first = learnt[0];
+                    let index = self.attach_clause(learnt);
+                    self.enqueue(first, Some(index));
+                }
+                self.activity_increment /= 0.95;
+                if conflicts_since_restart >= restart_limit {
+                    conflicts_since_restart = 0;
+                    restart_limit = restart_limit * 3 / 2;
+                    self.backtrack(0);
+                }
+            } else {
+                let Some(var) = self.pick_branch_var() else {
+                    return true;
+                };
+                self.trail_limits.push(self.trail.len());
+                self.enqueue(Literal::new(var, self.phase[var]), None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfies(solver: &Solver, clauses: &[Vec<Literal>]) -> bool {
+        clauses.iter().all(|clause| clause.iter().any(|literal| solver.value(*literal)))
+    }
+
+    #[test]
+    fn small_instances(){
+        let mut solver = Solver::new();
+        let (a, b, c) = (solver.new_var(), solver.new_var(), solver.new_var());
+        let clauses = vec![vec![a, b], vec![!a, b], vec![a, !b, c], vec![!c, !a, b]];
+        for clause in clauses.iter(){
+            solver.add_clause(clause);
+        }
+        assert!(solver.solve());
+        assert!(satisfies(&solver, &clauses));
+        let mut solver = Solver::new();
+        let (a, b) = (solver.new_var(), solver.new_var());
+        for clause in [[a, b], [!a, b], [a, !b], [!a, !b]] {
+            solver.add_clause(&clause);
+        }
+        assert!(!solver.solve());
+    }
+
+    //every pigeon sits in a hole and no hole holds two, unsatisfiable with more pigeons than holes
+    fn pigeonhole(pigeons: usize, holes: usize) -> Solver {
+        let mut solver = Solver::new();
+        let sits: Vec<Vec<Literal>> = (0..pigeons).map(|_| (0..holes).map(|_| solver.new_var()).collect()).collect();
+        for pigeon in sits.iter(){
+            solver.add_clause(pigeon);
+        }
+        for (first, pigeon) in sits.iter().enumerate(){
+            for other in sits[first + 1..].iter(){
+                for (a, b) in pigeon.iter().zip(other.iter()){
+                    solver.add_clause(&[!*a, !*b]);
+                }
+            }
+        }
+        solver
+    }
+
+    #[test]
+    fn pigeonhole_needs_learning(){
+        let mut solver = pigeonhole(3, 2);
+        assert!(!solver.solve());
+        assert!(solver.conflicts > 0);
+        let mut solver = pigeonhole(6, 5);
+        assert!(!solver.solve());
+        assert!(pigeonhole(5, 5).solve());
+    }
+
+    //an encoder and the function it should encode
+    type Gate = (fn(&mut Solver, Literal, &[Literal]), fn(&[bool]) -> bool);
+
+    //fixes the inputs and checks the output can only take the value of the gate
+    #[test]
+    fn gate_encodings_follow_their_truth_tables(){
+        let gates: [Gate; 3] = [
+            (Solver::add_or, |inputs| inputs.iter().any(|input| *input)),
+            (Solver::add_and, |inputs| inputs.iter().all(|input| *input)),
+            (Solver::add_xor, |inputs| inputs.iter().filter(|input| **input).count() % 2 == 1),
+        ];
+        for (gate, function) in gates {
+            for count in 1..=4 {
+                for combination in 0..1 << count {
+                    let values: Vec<bool> = (0..count).map(|bit| combination >> bit & 1 != 0).collect();
+                    for output_value in [false, true] {
+                        let mut solver = Solver::new();
+                        let inputs: Vec<Literal> = (0..count).map(|_| solver.new_var()).collect();
+                        let output = solver.new_var();
+                        gate(&mut solver, output, &inputs);
+                        for (input, value) in inputs.iter().zip(values.iter()){
+                            solver.add_clause(&[if *value { *input } else { !*input }]);
+                        }
+                        solver.add_clause(&[if output_value { output } else { !output }]);
+                        assert_eq!(solver.solve(), output_value == function(&values), "{:?} to {}", values, output_value);
+                    }
+                }
+            }
+        }
+    }
+}
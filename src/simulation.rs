@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use crate::content::ComponentData;
+use crate::MSPT_OPTIONS;
+
+//longest time the simulation thread keeps the canvas locked in one go
+const BATCH_MS: i64 = 4;
+
+pub struct SimulationControl {
+    pub running: AtomicBool,
+    pub mspt: AtomicI64,
+    pub ticks_per_second: AtomicU64,
+    frame_waiting: AtomicBool,
+    stop: AtomicBool,
+}
+
+//the canvas lives behind a mutex, the simulation thread ticks it in batches. the render loop only locks it to handle
+//input and copy what the frame shows, and draws with the lock released
+pub struct SimulationThread {
+    pub component_data: Arc<Mutex<ComponentData>>,
+    pub control: Arc<SimulationControl>,
+    handle: std::thread::JoinHandle<()>,
+}
+
+impl SimulationThread {
+    pub fn start(component_data: ComponentData, mspt: i64) -> Self {
+        let component_data = Arc::new(Mutex::new(component_data));
+        let control = Arc::new(SimulationControl{
+            running: AtomicBool::new(false),
+            mspt: AtomicI64::new(mspt),
+            ticks_per_second: AtomicU64::new(0),
+            frame_waiting: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+        });
+        let thread_data = component_data.clone();
+        let thread_control = control.clone();
+        let handle = std::thread::spawn(move || run(thread_data, thread_control));
+        Self{
+            component_data,
+            control,
+            handle,
+        }
+    }
+
+    //makes the simulation thread step aside so a frame never waits for a whole batch
+    pub fn lock(&self) -> MutexGuard<'_, ComponentData> {
+        self.control.frame_waiting.store(true, Ordering::SeqCst);
+        let guard = self.component_data.lock().unwrap();
+        self.control.frame_waiting.store(false, Ordering::SeqCst);
+        guard
+    }
+
+    pub fn set_running(&self, running: bool){
+        self.control.running.store(running, Ordering::SeqCst);
+    }
+
+    pub fn set_mspt(&self, mspt: i64){
+        self.control.mspt.store(mspt, Ordering::SeqCst);
+    }
+
+    pub fn ticks_per_second(&self) -> u64 {
+        self.control.ticks_per_second.load(Ordering::SeqCst)
+    }
+
+    pub fn stop(self) -> ComponentData {
+        self.control.stop.store(true, Ordering::SeqCst);
+        self.handle.join().expect("simulation thread panicked");
+        Arc::try_unwrap(self.component_data).ok().expect("canvas is still shared").into_inner().unwrap()
+    }
+}
+
+//ticks are scheduled from the moment the speed was set, so a slow frame is caught up instead of lowering the rate
+fn run(component_data: Arc<Mutex<ComponentData>>, control: Arc<SimulationControl>){
+    let mut stopwatch = stopwatch::Stopwatch::start_new();
+    let mut ticks_done = 0i64;
+    let mut mspt = -1;
+    let mut running = false;
+    let mut measure_stopwatch = stopwatch::Stopwatch::start_new();
+    let mut measured_ticks = 0u64;
+    while !control.stop.load(Ordering::SeqCst) {
+        let new_mspt = control.mspt.load(Ordering::SeqCst);
+        let new_running = control.running.load(Ordering::SeqCst);
+        if new_mspt != mspt || new_running != running {
+            mspt = new_mspt;
+            running = new_running;
+            stopwatch.restart();
+            ticks_done = 0;
+        }
+        if measure_stopwatch.elapsed_ms() >= 1000 {
+            control.ticks_per_second.store(measured_ticks * 1000 / measure_stopwatch.elapsed_ms() as u64, Ordering::SeqCst);
+            measure_stopwatch.restart();
+            measured_ticks = 0;
+        }
+        if !running || mspt == MSPT_OPTIONS[0] {
+            control.ticks_per_second.store(0, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            continue;
+        }
+        let due = if mspt == 0 { i64::MAX } else { stopwatch.elapsed_ms() / mspt + 1 - ticks_done };
+        if due <= 0 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            continue;
+        }
+        while control.frame_waiting.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        let mut data = component_data.lock().unwrap();
+        if !control.running.load(Ordering::SeqCst) {//simulation was stopped while waiting for the lock
+            continue;
+        }
+        if data.breakpoint_hit.is_some() {//the render loop pauses the simulation when it sees the breakpoint
+            drop(data);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            continue;
+        }
+        let batch_stopwatch = stopwatch::Stopwatch::start_new();
+        let mut count = 0;
+        while count < due && batch_stopwatch.elapsed_ms() < BATCH_MS && !control.frame_waiting.load(Ordering::SeqCst) && data.breakpoint_hit.is_none() {
+            data.update_canvas();
+            count += 1;
+        }
+        drop(data);
+        ticks_done += count;
+        measured_ticks += count as u64;
+    }
+}
@@ -0,0 +1,107 @@
+use crate::content::{ComponentData, ComponentType, HEIGHT, NAMES, WIDTH};
+use crate::region::describe_group;
+
+//counts are indexed by component type, fan_in and fan_out count the groups for every number of inputs or outputs
+pub struct Stats {
+    pub cells: Vec<usize>,
+    pub groups: Vec<usize>,
+    pub fan_in: Vec<usize>,
+    pub fan_out: Vec<usize>,
+    pub widest_fan_in: Option<usize>,
+    pub widest_fan_out: Option<usize>,
+    pub nets: usize,
+    pub bounding_box: Option<((usize, usize), (usize, usize))>,
+}
+
+fn count(distribution: &mut Vec<usize>, value: usize) {
+    if distribution.len() <= value {
+        distribution.resize(value + 1, 0);
+    }
+    distribution[value] += 1;
+}
+
+//groups are taken from logic_components, so the canvas has to be compiled
+pub fn collect(component_data: &ComponentData) -> Stats {
+    let mut stats = Stats{cells: vec![0; ComponentType::NUM_COMPONENTS as usize], groups: vec![0; ComponentType::NUM_COMPONENTS as usize],
+        fan_in: vec![], fan_out: vec![], widest_fan_in: None, widest_fan_out: None, nets: 0, bounding_box: None};
+    for (x, column) in component_data.array.iter().enumerate(){
+        for (y, component) in column.iter().enumerate(){
+            if component.component_type == ComponentType::NOTHING {
+                continue;
+            }
+            stats.cells[component.component_type as usize] += 1;
+            stats.bounding_box = Some(match stats.bounding_box {
+                Some(((x1, y1), (x2, y2))) => ((x1.min(x), y1.min(y)), (x2.max(x), y2.max(y))),
+                None => ((x, y), (x, y))
+            });
+        }
+    }
+    let logic_components = &component_data.logic_components;
+    for (group, logic_component) in logic_components.iter().enumerate(){
+        stats.groups[logic_component.component_type as usize] += 1;
+        if logic_component.component_type == ComponentType::WIRE {
+            stats.nets += 1;
+        }
+        count(&mut stats.fan_in, logic_component.component_before.len());
+        count(&mut stats.fan_out, logic_component.component_after.len());
+        if stats.widest_fan_in.is_none_or(|widest| logic_components[widest].component_before.len() < logic_component.component_before.len()) {
+            stats.widest_fan_in = Some(group);
+        }
+        if stats.widest_fan_out.is_none_or(|widest| logic_components[widest].component_after.len() < logic_component.component_after.len()) {
+            stats.widest_fan_out = Some(group);
+        }
+    }
+    stats
+}
+
+//small values are listed one by one, bigger ones in power of two buckets
+fn format_distribution(distribution: &[usize]) -> String {
+    let mut buckets = vec![];
+    let mut start = 0;
+    while start < distribution.len() {
+        let end = if start < 4 { start } else { (start.next_power_of_two() * 2 - 1).min(distribution.len() - 1) };
+        let total: usize = distribution[start..=end].iter().sum();
+        if total > 0 {
+            let name = if start == end { start.to_string() } else { format!("{}-{}", start, end) };
+            buckets.push(format!("{}: {}", name, total));
+        }
+        start = end + 1;
+    }
+    if buckets.is_empty() {
+        return "none".to_string();
+    }
+    buckets.join(", ")
+}
+
+impl Stats {
+    pub fn unused_percentage(&self) -> f64 {
+        let used: usize = self.cells.iter().sum();
+        100.0 - used as f64 * 100.0 / (WIDTH * HEIGHT) as f64
+    }
+
+    pub fn report(&self, component_data: &ComponentData) -> String {
+        let mut text = String::new();
+        text.push_str("component        cells  groups\n");
+        for (component_type, name) in NAMES.iter().enumerate().skip(1){
+            if self.cells[component_type] > 0 || self.groups[component_type] > 0 {
+                text.push_str(&format!("{:<15} {:>6} {:>7}\n", name, self.cells[component_type], self.groups[component_type]));
+            }
+        }
+        text.push_str(&format!("{:<15} {:>6} {:>7}\n", "total", self.cells.iter().sum::<usize>(), self.groups.iter().sum::<usize>()));
+        text.push_str(&format!("wire nets: {}\n", self.nets));
+        text.push_str(&format!("fan-in: {}\n", format_distribution(&self.fan_in)));
+        if let Some(group) = self.widest_fan_in {
+            text.push_str(&format!("widest fan-in: {} into {}\n", component_data.logic_components[group].component_before.len(), describe_group(component_data, group)));
+        }
+        text.push_str(&format!("fan-out: {}\n", format_distribution(&self.fan_out)));
+        if let Some(group) = self.widest_fan_out {
+            text.push_str(&format!("widest fan-out: {} from {}\n", component_data.logic_components[group].component_after.len(), describe_group(component_data, group)));
+        }
+        match self.bounding_box {
+            Some(((x1, y1), (x2, y2))) => text.push_str(&format!("used area: x{} y{} to x{} y{} ({} x {} cells)\n", x1, y1, x2, y2, x2 - x1 + 1, y2 - y1 + 1)),
+            None => text.push_str("used area: none\n")
+        }
+        text.push_str(&format!("unused canvas: {:.2}%\n", self.unused_percentage()));
+        text
+    }
+}
@@ -0,0 +1,52 @@
+use crate::content::{ComponentData, ComponentType};
+
+//builds a canvas for tests from rows of characters, the first character of the first row is at x0 y0
+//. nothing, w writer, - wire, + cross, r reader, & and, | or, ^ xor, ! not, n nand, x xnor, # comment,
+//c clock, l latch, L light, s seven segment, h hex display, f framebuffer, a assert
+pub fn canvas(rows: &[&str]) -> ComponentData {
+    let mut component_data = ComponentData::default();
+    draw(&mut component_data, 0, 0, rows);
+    component_data
+}
+
+pub fn draw(component_data: &mut ComponentData, x: usize, y: usize, rows: &[&str]){
+    for (row, text) in rows.iter().enumerate(){
+        for (column, character) in text.chars().enumerate(){
+            let component_type = match character {
+                'w' => ComponentType::WRITE_TO_WIRE,
+                '-' => ComponentType::WIRE,
+                '+' => ComponentType::CROSS,
+                'r' => ComponentType::READ_FROM_WIRE,
+                '&' => ComponentType::AND,
+                '|' => ComponentType::OR,
+                '^' => ComponentType::XOR,
+                '!' => ComponentType::NOT,
+                'n' => ComponentType::NAND,
+                'x' => ComponentType::XNOR,
+                '#' => ComponentType::COMMENT,
+                'c' => ComponentType::CLOCK,
+                'l' => ComponentType::LATCH,
+                'L' => ComponentType::LIGHT,
+                's' => ComponentType::SEVEN_SEGMENT,
+                'h' => ComponentType::HEX_DISPLAY,
+                'f' => ComponentType::FRAMEBUFFER,
+                'a' => ComponentType::ASSERT,
+                _ => ComponentType::NOTHING
+            };
+            component_data.array[x + column][y + row].component_type = component_type;
+        }
+    }
+}
+
+pub fn compiled(rows: &[&str]) -> ComponentData {
+    let mut component_data = canvas(rows);
+    component_data.compile_scene();
+    component_data
+}
+
+//the group of the cell at x y, the canvas has to be compiled
+pub fn group_at(component_data: &ComponentData, x: usize, y: usize) -> usize {
+    let group = component_data.array[x][y].belongs_to;
+    assert!(group != -1, "nothing compiled at x{} y{}", x, y);
+    group as usize
+}
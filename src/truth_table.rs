@@ -0,0 +1,158 @@
+use crate::content::{lane_pattern, ComponentData, DelayMode, LANES};
+use crate::region::{compile_region, find_ports, Ports};
+
+pub const MAX_INPUTS: usize = 16;
+pub const MAX_TICKS: u64 = 10000;
+
+//the inputs and the outputs of one combination
+pub type Row = (Vec<bool>, Vec<bool>);
+
+pub struct TruthTable {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+//runs the region in zero delay mode, 64 input combinations at once, one per lane
+//the first input is the lowest bit of the row number
+pub fn generate(component_data: &ComponentData, region: ((i32, i32), (i32, i32))) -> Result<TruthTable, String> {
+    let mut region_data = compile_region(component_data, region);
+    region_data.set_delay_mode(DelayMode::Zero);
+    let ports = find_ports(component_data, region, &region_data);
+    if ports.inputs.is_empty() || ports.outputs.is_empty() {
+        return Err(format!("found {} inputs and {} outputs, a truth table needs at least one of each", ports.inputs.len(), ports.outputs.len()));
+    }
+    if ports.inputs.len() > MAX_INPUTS {
+        return Err(format!("found {} inputs, at most {} are supported", ports.inputs.len(), MAX_INPUTS));
+    }
+    Ok(TruthTable{
+        inputs: ports.inputs.iter().map(|port| port.name.clone()).collect(),
+        outputs: ports.outputs.iter().map(|port| port.name.clone()).collect(),
+        rows: generate_rows(&mut region_data, &ports)?,
+    })
+}
+
+//the rows of a region compiled in zero delay mode, with between one and MAX_INPUTS inputs
+pub fn generate_rows(region_data: &mut ComponentData, ports: &Ports) -> Result<Vec<Row>, String> {
+    let combinations = 1u64 << ports.inputs.len();
+    let mut rows = vec![];
+    for batch in 0..combinations.div_ceil(LANES as u64) {
+        for (bit, port) in ports.inputs.iter().enumerate(){
+            let lanes = if bit < 6 { lane_pattern(bit) } else if batch >> (bit - 6) & 1 != 0 { !0 } else { 0 };
+            region_data.drive_group(port.group, lanes);
+        }
+        if !region_data.run_until_stable(MAX_TICKS) {
+            return Err(format!("the region doesn't settle within {} ticks, it probably contains a clock or an oscillating loop", MAX_TICKS));
+        }
+        for lane in 0..LANES {
+            let combination = batch * LANES as u64 + lane as u64;
+            if combination >= combinations {
+                break;
+            }
+            let inputs = (0..ports.inputs.len()).map(|bit| combination >> bit & 1 != 0).collect();
+            let outputs = ports.outputs.iter().map(|port| region_data.program.state[port.group] >> lane & 1 != 0).collect();
+            rows.push((inputs, outputs));
+        }
+    }
+    Ok(rows)
+}
+
+fn format_bits(bits: &[bool]) -> Vec<&'static str> {
+    bits.iter().map(|bit| if *bit { "1" } else { "0" }).collect()
+}
+
+impl TruthTable {
+    pub fn to_csv(&self) -> String {
+        let mut text = [self.inputs.clone(), self.outputs.clone()].concat().join(",");
+        text.push('\n');
+        for row in self.rows.iter(){
+            text.push_str(&[format_bits(&row.0), format_bits(&row.1)].concat().join(","));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("| {} |\n", [self.inputs.clone(), self.outputs.clone()].concat().join(" | "));
+        text.push_str(&format!("|{}|\n", vec!["---"; self.inputs.len() + self.outputs.len()].join("|")));
+        for row in self.rows.iter(){
+            text.push_str(&format!("| {} |\n", [format_bits(&row.0), format_bits(&row.1)].concat().join(" | ")));
+        }
+        text
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let text = match extension.as_str() {
+            "csv" => self.to_csv(),
+            "md" => self.to_markdown(),
+            _ => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("unsupported table format: {}", path.display())))
+        };
+        std::fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_canvas::canvas;
+
+    //three horizontal input wires, crosses let the taps through to one and gate per pair, the gates write onto the same wire
+    const MAJORITY: [&str; 10] = [
+        "lw------------",
+        "...-...-......",
+        "lw-+---+------",
+        "...-.-.-...-..",
+        "lw-+-+-+---+--",
+        "...-.-.-.-.-.-",
+        "...r.r.r.r.r.r",
+        "...&&&.&&&.&&&",
+        "....w...w...w.",
+        "....---------rL",
+    ];
+
+    //one xor group reading every input, it drives a light
+    fn parity(inputs: usize) -> Vec<String> {
+        (0..inputs * 2 - 1).map(|row| match row {
+            0 => "lw-r^w-rL".to_string(),
+            _ if row % 2 == 0 => "lw-r^".to_string(),
+            _ => "....^".to_string()
+        }).collect()
+    }
+
+    fn check(rows: &[&str], inputs: usize, function: impl Fn(&[bool]) -> bool){
+        let component_data = canvas(rows);
+        let width = rows.iter().map(|row| row.len()).max().unwrap() as i32;
+        let table = generate(&component_data, ((0, 0), (width - 1, rows.len() as i32 - 1))).unwrap();
+        assert_eq!((table.inputs.len(), table.outputs.len()), (inputs, 1));
+        assert_eq!(table.rows.len(), 1 << inputs);
+        for (combination, row) in table.rows.iter().enumerate(){
+            assert!(row.0.iter().enumerate().all(|(bit, input)| *input == (combination >> bit & 1 != 0)), "inputs of row {}", combination);
+            assert_eq!(row.1, vec![function(&row.0)], "row {}", combination);
+        }
+    }
+
+    #[test]
+    fn rows_of_a_partial_batch(){
+        check(&["lw--r", "....^w-rL", "lw--r"], 2, |inputs| inputs[0] ^ inputs[1]);
+        check(&MAJORITY, 3, |inputs| inputs.iter().filter(|input| **input).count() >= 2);
+    }
+
+    //above 6 inputs the higher bits come from the batch number
+    #[test]
+    fn rows_of_several_batches(){
+        for inputs in [6, 7, 9] {
+            let rows = parity(inputs);
+            check(&rows.iter().map(|row| row.as_str()).collect::<Vec<&str>>(), inputs, |inputs| inputs.iter().filter(|input| **input).count() % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn at_most_max_inputs(){
+        let rows = parity(MAX_INPUTS);
+        check(&rows.iter().map(|row| row.as_str()).collect::<Vec<&str>>(), MAX_INPUTS, |inputs| inputs.iter().filter(|input| **input).count() % 2 == 1);
+        let rows = parity(MAX_INPUTS + 1);
+        let component_data = canvas(&rows.iter().map(|row| row.as_str()).collect::<Vec<&str>>());
+        assert!(generate(&component_data, ((0, 0), (8, rows.len() as i32 - 1))).is_err());
+    }
+}
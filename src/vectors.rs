@@ -0,0 +1,65 @@
+use crate::content::ComponentData;
+
+//a test vector file lists latch toggles, one "<tick> toggle <x> <y>" per line, # starts a comment
+//a toggle happens right before the tick with that number is simulated, tick 0 is the first one after compiling
+#[derive(Default)]
+pub struct TestVectors {
+    pub toggles: Vec<(u64, (usize, usize))>,
+}
+
+impl TestVectors {
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut vectors = TestVectors::default();
+        for (index, line) in text.lines().enumerate(){
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.len() != 4 || words[1] != "toggle" {
+                return Err(format!("line {}: expected <tick> toggle <x> <y>", index + 1));
+            }
+            let numbers: Vec<u64> = words.iter().enumerate().filter(|(i, _)| *i != 1).filter_map(|(_, word)| word.parse().ok()).collect();
+            if numbers.len() != 3 || !ComponentData::are_coordinates_in_bounds(numbers[1] as i32, numbers[2] as i32) {
+                return Err(format!("line {}: the tick and position have to be numbers on the canvas", index + 1));
+            }
+            vectors.toggles.push((numbers[0], (numbers[1] as usize, numbers[2] as usize)));
+        }
+        vectors.toggles.sort_by_key(|toggle| toggle.0);
+        Ok(vectors)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (tick, position) in self.toggles.iter(){
+            text.push_str(&format!("{} toggle {} {}\n", tick, position.0, position.1));
+        }
+        text
+    }
+
+    pub fn save(&self, path: &std::path::Path, comment: &str) -> std::io::Result<()> {
+        let header: String = comment.lines().map(|line| format!("# {}\n", line)).collect();
+        std::fs::write(path, header + &self.to_text())
+    }
+
+    pub fn last_tick(&self) -> Option<u64> {
+        self.toggles.last().map(|toggle| toggle.0)
+    }
+
+    //toggles the latches due before the current tick of component_data, errors on positions without a latch
+    pub fn apply(&self, component_data: &mut ComponentData) -> Result<(), String> {
+        let tick = component_data.ticks;
+        let start = self.toggles.partition_point(|toggle| toggle.0 < tick);
+        for (_, position) in self.toggles[start..].iter().take_while(|toggle| toggle.0 == tick){
+            if !component_data.toggle_latch(position.0, position.1) {
+                return Err(format!("tick {}: there is no latch at x{} y{}", tick, position.0, position.1));
+            }
+        }
+        Ok(())
+    }
+}